@@ -0,0 +1,5 @@
+-- Orders belong to the user who created them. Orders created before this
+-- migration have no owner and are therefore not visible to anyone.
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS user_id INT REFERENCES app_users (id);
+
+CREATE INDEX IF NOT EXISTS idx_orders_user_id ON orders (user_id);
@@ -0,0 +1,7 @@
+-- Access tokens revoked before their natural expiry, keyed by the `jti` claim.
+-- Rows can be purged once `expires_at` has passed.
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti TEXT PRIMARY KEY,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP NOT NULL DEFAULT now()
+);
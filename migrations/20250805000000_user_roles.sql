@@ -0,0 +1,4 @@
+-- Roles granted to a user, copied into the access token's `roles` claim.
+-- There is no endpoint to grant the first admin; do it by hand:
+--   UPDATE app_users SET roles = array_append(roles, 'admin') WHERE user_login = '...';
+ALTER TABLE app_users ADD COLUMN IF NOT EXISTS roles TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,23 @@
+-- Failed login attempts, counted separately per submitted login and per client
+-- IP. Unknown logins are tracked exactly like real ones so lockouts do not
+-- reveal which accounts exist.
+CREATE TABLE IF NOT EXISTS login_attempts (
+    scope TEXT NOT NULL,
+    key TEXT NOT NULL,
+    failures INT NOT NULL DEFAULT 0,
+    last_failure_at TIMESTAMP NOT NULL DEFAULT now(),
+    locked_until TIMESTAMP,
+    PRIMARY KEY (scope, key)
+);
+
+-- Security-relevant events such as lockouts.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id SERIAL PRIMARY KEY,
+    event TEXT NOT NULL,
+    user_login TEXT,
+    client_ip TEXT,
+    details TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log (created_at);
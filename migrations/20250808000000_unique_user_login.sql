@@ -0,0 +1,5 @@
+-- One account per login. Duplicate logins created before this constraint must
+-- be merged or renamed by hand before the migration can apply.
+CREATE UNIQUE INDEX IF NOT EXISTS uq_app_users_user_login ON app_users (user_login);
+
+DROP INDEX IF EXISTS idx_app_users_user_login;
@@ -0,0 +1,18 @@
+-- Single-use password reset tokens, stored as SHA-256 hashes.
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES app_users (id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user_id ON password_reset_tokens (user_id);
+
+-- Access tokens issued to a user before `not_before` are rejected, which ends
+-- every session at once (password change or reset). One row per user.
+CREATE TABLE IF NOT EXISTS token_cutoffs (
+    user_id INT PRIMARY KEY REFERENCES app_users (id) ON DELETE CASCADE,
+    not_before TIMESTAMP NOT NULL
+);
@@ -0,0 +1,21 @@
+-- Email address and its verification state. Accounts created before this
+-- migration have no email and are treated as verified so that turning on
+-- REQUIRE_EMAIL_VERIFICATION does not lock them out.
+ALTER TABLE app_users ADD COLUMN IF NOT EXISTS email TEXT;
+ALTER TABLE app_users ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMP;
+
+UPDATE app_users SET email_verified_at = created_at WHERE email IS NULL AND email_verified_at IS NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_app_users_email ON app_users (lower(email));
+
+-- Single-use email verification tokens, stored as SHA-256 hashes.
+CREATE TABLE IF NOT EXISTS email_verification_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES app_users (id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_verification_tokens_user_id ON email_verification_tokens (user_id);
@@ -0,0 +1,17 @@
+-- TOTP second factor. `totp_secret` is stored at enrollment but only enforced
+-- once the confirmation step sets `totp_enabled_at`.
+ALTER TABLE app_users ADD COLUMN IF NOT EXISTS totp_secret TEXT;
+ALTER TABLE app_users ADD COLUMN IF NOT EXISTS totp_enabled_at TIMESTAMP;
+-- Last time step a code was accepted for, so a code cannot be replayed.
+ALTER TABLE app_users ADD COLUMN IF NOT EXISTS totp_last_step BIGINT;
+
+-- Single-use recovery codes, stored as SHA-256 hashes.
+CREATE TABLE IF NOT EXISTS mfa_recovery_codes (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES app_users (id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_mfa_recovery_codes_user_id ON mfa_recovery_codes (user_id);
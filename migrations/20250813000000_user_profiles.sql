@@ -0,0 +1,9 @@
+-- Soft deletion for users, matching `orders.is_active`, and a last-modified
+-- timestamp for profile edits.
+ALTER TABLE app_users ADD COLUMN IF NOT EXISTS is_active BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE app_users ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP;
+
+UPDATE app_users SET updated_at = created_at WHERE updated_at IS NULL;
+
+ALTER TABLE app_users ALTER COLUMN updated_at SET DEFAULT now();
+ALTER TABLE app_users ALTER COLUMN updated_at SET NOT NULL;
@@ -3,24 +3,26 @@ use actix_web::{HttpResponse, Responder};
 use sqlx::PgPool;
 
 use crate::repository::health_check::HealthCheckRepo;
+use crate::utils::errors::AppError;
+use crate::utils::hashing_pool::HashingPool;
 
 use super::api_responses::ApiResponse;
 
-pub async fn check_health(pool: Data<PgPool>) -> impl Responder {
-    let status = match HealthCheckRepo::check_status(&pool).await {
-        Ok(status) => status,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occured :: {:?}", e),
-                results: None,
-            })
-        }
-    };
-    HttpResponse::Ok().json(ApiResponse {
+pub async fn check_health(pool: Data<PgPool>) -> Result<HttpResponse, AppError> {
+    let status = HealthCheckRepo::check_status(&pool).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse {
         status: 200,
         msg: String::from("Server is Healthy and Running !!"),
         results: Some(status),
+    }))
+}
+
+/// Queue depth and wait times of the password hashing pool, for spotting login bursts.
+pub async fn hashing_stats(hasher: Data<HashingPool>) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: String::from("Password hashing stats !!"),
+        results: Some(hasher.stats()),
     })
 }
 
@@ -0,0 +1,174 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::web::Data;
+use actix_web::{HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+use crate::repository::mfa_repo::MfaRepo;
+use crate::utils::config::AppConfig;
+use crate::utils::errors::{AppError, TokenError};
+use crate::utils::hashing_pool::HashingPool;
+use crate::utils::jwt_impl::{decode_mfa_token, generate_mfa_token};
+use crate::utils::keystore::KeyStore;
+use crate::utils::throttle::{client_ip, LoginThrottle};
+use crate::utils::totp::{
+    generate_recovery_codes, generate_secret, hash_recovery_code, matching_step, totp,
+};
+use crate::utils::types::{
+    AuthenticatedUser, CurrentPassword, MfaChallenge, MfaState, MfaVerify, RecoveryCodes, TotpCode,
+    TotpSetup,
+};
+use crate::utils::validation::ValidatedJson;
+
+use super::api_responses::ApiResponse;
+use super::password::verify_current_password;
+use super::session::{session_response, session_roles, start_session};
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+async fn mfa_state(user_id: i32, pool: &Data<PgPool>) -> Result<MfaState, AppError> {
+    MfaRepo::fetch_state(user_id, pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(String::from("User not found !!")))
+}
+
+/// First-step answer for accounts with TOTP: a short-lived token for `verify_mfa` instead of a
+/// session.
+pub fn mfa_challenge(
+    user_id: i32,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<MfaChallenge, AppError> {
+    Ok(MfaChallenge {
+        mfa_required: true,
+        mfa_token: generate_mfa_token(user_id, config, keys)?,
+        expires_in: config.mfa_token_ttl.num_seconds(),
+    })
+}
+
+/// Starts TOTP enrollment: stores a new secret and returns it with an otpauth URI for
+/// authenticator apps. Nothing is enforced until `confirm_totp` succeeds, and calling this
+/// again before that replaces the secret.
+///
+/// The current password is required, so a hijacked session alone cannot lock the owner out
+/// with a secret they do not have. SSO users set a password through `forgot_password` first.
+/// Wrong passwords count towards the `user_login` lockouts; see `verify_current_password`.
+pub async fn setup_totp(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    payload: ValidatedJson<CurrentPassword>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    hasher: Data<HashingPool>,
+) -> Result<HttpResponse, AppError> {
+    let sec = payload.into_inner().sec;
+    verify_current_password(user.user_id, sec, &req, &pool, &config, &hasher).await?;
+
+    let state = mfa_state(user.user_id, &pool).await?;
+    let secret = generate_secret();
+    let otpauth_uri = totp(&secret, &config.totp_issuer, &state.user_login)?.get_url();
+    if state.totp_enabled || !MfaRepo::set_pending_secret(user.user_id, &secret, &pool).await? {
+        return Err(AppError::Conflict(String::from("TOTP already enabled !!")));
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: String::from("TOTP secret generated, confirm it with a code !!"),
+        results: Some(TotpSetup {
+            secret,
+            otpauth_uri,
+        }),
+    }))
+}
+
+/// Finishes enrollment with a code from the authenticator and returns the recovery codes.
+///
+/// The codes are shown only here; each one can replace a TOTP code once.
+pub async fn confirm_totp(
+    user: AuthenticatedUser,
+    payload: ValidatedJson<TotpCode>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+) -> Result<HttpResponse, AppError> {
+    let state = mfa_state(user.user_id, &pool).await?;
+    if state.totp_enabled {
+        return Err(AppError::Conflict(String::from("TOTP already enabled !!")));
+    }
+    let secret = state
+        .totp_secret
+        .ok_or_else(|| AppError::Validation(String::from("Start TOTP setup first !!")))?;
+    let generator = totp(&secret, &config.totp_issuer, &state.user_login)?;
+    let step = matching_step(&generator, &payload.code, unix_now())
+        .ok_or_else(|| AppError::Validation(String::from("Invalid TOTP code !!")))?;
+
+    let (codes, hashes): (Vec<String>, Vec<String>) = generate_recovery_codes().into_iter().unzip();
+    if !MfaRepo::enable(user.user_id, step as i64, &hashes, &pool).await? {
+        return Err(AppError::Conflict(String::from("TOTP already enabled !!")));
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: String::from("TOTP enabled, store the recovery codes safely !!"),
+        results: Some(RecoveryCodes {
+            recovery_codes: codes,
+        }),
+    }))
+}
+
+/// Second login step: exchanges the `mfa_token` from `user_login` and a TOTP or recovery code
+/// for a session in the same shape as `user_login`.
+///
+/// Each TOTP code is accepted once. Wrong codes count towards the same lockout as wrong
+/// passwords; see `LoginThrottle`.
+pub async fn verify_mfa(
+    req: HttpRequest,
+    payload: ValidatedJson<MfaVerify>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    keys: Data<KeyStore>,
+) -> Result<HttpResponse, AppError> {
+    let payload = payload.into_inner();
+    let claims = decode_mfa_token(&payload.mfa_token, &config, &keys)
+        .map_err(|e| AppError::InvalidToken(TokenError::from(e)))?;
+    let user_id = claims.sub;
+    let state = mfa_state(user_id, &pool).await?;
+    let client_ip = client_ip(&req);
+    LoginThrottle::check(&state.user_login, &client_ip, &pool).await?;
+
+    let accepted = match (&payload.code, &payload.recovery_code, &state.totp_secret) {
+        (Some(code), None, Some(secret)) if state.totp_enabled => {
+            let generator = totp(secret, &config.totp_issuer, &state.user_login)?;
+            match matching_step(&generator, code, unix_now()) {
+                Some(step) => MfaRepo::claim_step(user_id, step as i64, &pool).await?,
+                None => false,
+            }
+        }
+        (None, Some(recovery_code), _) if state.totp_enabled => {
+            MfaRepo::use_recovery_code(user_id, &hash_recovery_code(recovery_code), &pool).await?
+        }
+        (None, None, _) | (Some(_), Some(_), _) => {
+            return Err(AppError::Validation(String::from(
+                "Send either a code or a recovery code !!",
+            )));
+        }
+        _ => false,
+    };
+    if !accepted {
+        LoginThrottle::record_failure(&state.user_login, &client_ip, &config, &pool).await?;
+        return Err(AppError::Unauthorized(String::from("Invalid MFA code !!")));
+    }
+    LoginThrottle::record_success(&state.user_login, &pool).await?;
+
+    let roles = session_roles(state.roles, state.totp_enabled);
+    let tokens = start_session(user_id, &roles, &pool, &config, &keys).await?;
+    Ok(session_response(&tokens, &config).json(ApiResponse {
+        status: 200,
+        msg: String::from("User Loggedin !!"),
+        results: Some(tokens),
+    }))
+}
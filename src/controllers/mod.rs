@@ -1,5 +1,12 @@
 pub mod api_responses;
 pub mod health;
+pub mod mfa;
+pub mod oidc;
 pub mod orders;
+pub mod password;
+pub mod profile;
+pub mod session;
 pub mod status;
+pub mod tokens;
 pub mod user;
+pub mod verification;
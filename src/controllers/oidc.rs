@@ -0,0 +1,170 @@
+use actix_web::http::header;
+use actix_web::web::{Data, Query};
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::Duration;
+use sqlx::PgPool;
+
+use crate::repository::identity_repo::IdentityRepo;
+use crate::repository::user_repo::UserRepo;
+use crate::utils::config::AppConfig;
+use crate::utils::constants::{OIDC_COOKIE_NAME, OIDC_LOGIN_PREFIX};
+use crate::utils::errors::AppError;
+use crate::utils::hashing_pool::HashingPool;
+use crate::utils::helpers::{build_oidc_cookie, constant_time_eq, random_token, sha256_hex};
+use crate::utils::jwt_impl::{decode_oidc_state_token, generate_oidc_state_token};
+use crate::utils::keystore::KeyStore;
+use crate::utils::oidc::OidcClient;
+use crate::utils::types::{IdTokenClaims, OidcCallback};
+
+use super::api_responses::ApiResponse;
+use super::mfa::mfa_challenge;
+use super::session::{session_response, session_roles, start_session};
+
+/// How long the user may spend at the provider before the login has to be started again.
+const LOGIN_STATE_TTL_MINUTES: i64 = 10;
+
+fn oidc_client(oidc: Option<Data<OidcClient>>) -> Result<Data<OidcClient>, AppError> {
+    oidc.ok_or_else(|| AppError::NotFound(String::from("SSO login is not configured !!")))
+}
+
+/// Starts an SSO login: redirects the browser to the provider.
+///
+/// `state`, `nonce` and the PKCE verifier are kept in a signed, short-lived cookie scoped to
+/// the OIDC endpoints, so nothing is stored server side until the login completes.
+pub async fn oidc_start(
+    oidc: Option<Data<OidcClient>>,
+    config: Data<AppConfig>,
+    keys: Data<KeyStore>,
+) -> Result<HttpResponse, AppError> {
+    let oidc = oidc_client(oidc)?;
+    let state = random_token(24);
+    let nonce = random_token(24);
+    let verifier = random_token(32);
+    let location = oidc.authorization_url(&state, &nonce, &verifier).await?;
+
+    let ttl = Duration::minutes(LOGIN_STATE_TTL_MINUTES);
+    let login_state = generate_oidc_state_token(&state, &nonce, &verifier, ttl, &config, &keys)?;
+    Ok(HttpResponse::Found()
+        .cookie(build_oidc_cookie(login_state, ttl))
+        .insert_header((header::LOCATION, location))
+        .finish())
+}
+
+/// Completes an SSO login and starts a session in the same shape as `user_login`.
+///
+/// The code is redeemed with the PKCE verifier from the login cookie and the ID token is
+/// validated against the provider's keys. The user is looked up by issuer and subject and
+/// created on first login; see `IdentityRepo::provision`.
+///
+/// The provider only replaces the password: accounts with TOTP get the same `MfaChallenge`
+/// as `user_login` and finish through `verify_mfa`.
+pub async fn oidc_callback(
+    req: HttpRequest,
+    query: Query<OidcCallback>,
+    oidc: Option<Data<OidcClient>>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    keys: Data<KeyStore>,
+    hasher: Data<HashingPool>,
+) -> Result<HttpResponse, AppError> {
+    let oidc = oidc_client(oidc)?;
+    let query = query.into_inner();
+    if let Some(error) = query.error {
+        return Err(AppError::Unauthorized(format!(
+            "Identity provider refused the login :: {}",
+            query.error_description.unwrap_or(error)
+        )));
+    }
+
+    let login_state = req
+        .cookie(OIDC_COOKIE_NAME)
+        .and_then(|cookie| decode_oidc_state_token(cookie.value(), &config, &keys).ok())
+        .ok_or_else(|| AppError::Unauthorized(String::from("Login state missing or expired !!")))?;
+    let (Some(code), Some(state)) = (query.code, query.state) else {
+        return Err(AppError::Validation(String::from(
+            "Missing code or state !!",
+        )));
+    };
+    if !constant_time_eq(&state, &login_state.state) {
+        return Err(AppError::Unauthorized(String::from(
+            "Login state mismatch !!",
+        )));
+    }
+
+    let id_token = oidc.exchange_code(&code, &login_state.verifier).await?;
+    let claims = oidc
+        .validate_id_token(&id_token, &login_state.nonce)
+        .await?;
+    let user_id = match IdentityRepo::find_user(oidc.issuer(), &claims.sub, &pool).await? {
+        Some((user_id, true)) => user_id,
+        Some((_, false)) => {
+            return Err(AppError::Forbidden(String::from("Account deactivated !!")));
+        }
+        None => provision_user(oidc.issuer(), &claims, &pool, &hasher).await?,
+    };
+
+    let mut state_cookie = build_oidc_cookie(String::new(), Duration::zero());
+    state_cookie.make_removal();
+    let (roles, totp_enabled) = UserRepo::fetch_roles(user_id, &pool).await?;
+    if totp_enabled {
+        return Ok(HttpResponse::Ok().cookie(state_cookie).json(ApiResponse {
+            status: 200,
+            msg: String::from("MFA code required !!"),
+            results: Some(mfa_challenge(user_id, &config, &keys)?),
+        }));
+    }
+
+    let roles = session_roles(roles, totp_enabled);
+    let tokens = start_session(user_id, &roles, &pool, &config, &keys).await?;
+    Ok(session_response(&tokens, &config)
+        .cookie(state_cookie)
+        .json(ApiResponse {
+            status: 200,
+            msg: String::from("User Loggedin !!"),
+            results: Some(tokens),
+        }))
+}
+
+/// Stable login for an SSO identity, under the prefix `RegisterUser` refuses.
+fn sso_login(issuer: &str, subject: &str) -> String {
+    format!(
+        "{}{}",
+        OIDC_LOGIN_PREFIX,
+        &sha256_hex(&format!("{}|{}", issuer, subject))[..20]
+    )
+}
+
+/// SSO users get a derived, stable `user_login` and a random password nobody knows; they can
+/// still set one through `forgot_password`.
+async fn provision_user(
+    issuer: &str,
+    claims: &IdTokenClaims,
+    pool: &Data<PgPool>,
+    hasher: &HashingPool,
+) -> Result<i32, AppError> {
+    let user_login = sso_login(issuer, &claims.sub);
+    let user_name = claims
+        .name
+        .as_deref()
+        .or(claims.preferred_username.as_deref())
+        .or(claims.email.as_deref())
+        .unwrap_or(&user_login)
+        .to_string();
+    let sec_hash = hasher.hash(random_token(32)).await?;
+    IdentityRepo::provision(issuer, claims, &user_name, &user_login, &sec_hash, pool).await
+}
+
+#[cfg(test)]
+mod tests_oidc {
+    use super::*;
+    use crate::utils::validation::not_reserved_login;
+
+    #[test]
+    fn sso_logins_are_stable_and_cannot_be_registered() {
+        let login = sso_login("https://idp.example", "user-1");
+        assert_eq!(login, sso_login("https://idp.example", "user-1"));
+        assert_ne!(login, sso_login("https://idp.example", "user-2"));
+        assert_ne!(login, sso_login("https://other.example", "user-1"));
+        assert!(not_reserved_login(&login).is_err());
+    }
+}
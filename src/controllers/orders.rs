@@ -1,87 +1,96 @@
-use actix_web::web::{Data, Json, Query};
-use actix_web::{HttpResponse, Responder};
+use actix_web::web::{Data, Query};
+use actix_web::HttpResponse;
 use sqlx::PgPool;
 
 use crate::repository::order_repo::OrderRepo;
-use crate::utils::types::{Order, SingleOrder};
+use crate::utils::errors::AppError;
+use crate::utils::types::{AuthenticatedUser, Order, SingleOrder};
+use crate::utils::validation::ValidatedJson;
 
 use super::api_responses::ApiResponse;
 
-pub async fn get_one_order(query: Query<SingleOrder>, pool: Data<PgPool>) -> impl Responder {
+/// Orders owned by someone else are reported exactly like missing ones.
+fn order_not_found(order_id: &i32) -> AppError {
+    AppError::NotFound(format!("Order ID :: {} not found !!", order_id))
+}
+
+pub async fn get_one_order(
+    user: AuthenticatedUser,
+    query: Query<SingleOrder>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
     let order_id = &query.order_id;
-    let res = match OrderRepo::get_one_order_detail(order_id, &pool).await {
-        Ok(res) => res,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occured !! {:?}", e),
-                results: None,
-            });
-        }
-    };
-    HttpResponse::Ok().json(ApiResponse {
+    let res = OrderRepo::get_one_order_detail(order_id, user.user_id, &pool)
+        .await?
+        .ok_or_else(|| order_not_found(order_id))?;
+    Ok(HttpResponse::Ok().json(ApiResponse {
         status: 200,
         msg: "Details Fetched !!".to_string(),
         results: Some(res),
-    })
+    }))
 }
 
-pub async fn get_order_list(pool: Data<PgPool>) -> impl Responder {
-    let order_list = match OrderRepo::fetch_orders(&pool).await {
-        Ok(res) => res,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occured !! {:?}", e),
-                results: None,
-            });
-        }
-    };
-    HttpResponse::Ok().json(ApiResponse {
+pub async fn get_order_list(
+    user: AuthenticatedUser,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let order_list = OrderRepo::fetch_orders(user.user_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse {
         status: 200,
         msg: format!("Order List fetched !! {} Records", order_list.len()),
         results: Some(order_list),
-    })
+    }))
 }
 
-pub async fn remove_order(req: Query<SingleOrder>, pool: Data<PgPool>) -> impl Responder {
+pub async fn remove_order(
+    user: AuthenticatedUser,
+    req: Query<SingleOrder>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
     let order_id = req.order_id;
-    let res = match OrderRepo::deactivate_order(&order_id, &pool).await {
-        Ok(res) => res,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occured !! {:?}", e),
-                results: None,
-            });
-        }
-    };
-    HttpResponse::Ok().json(ApiResponse::<String> {
+    let res = OrderRepo::deactivate_order(&order_id, user.user_id, &pool).await?;
+    if res == 0 {
+        return Err(order_not_found(&order_id));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
         status: 200,
         msg: format!(
             "OK Rows effected {}!!!\n Order ID :: {:?}, Removed!!!",
             res, order_id
         ),
         results: None,
-    })
+    }))
 }
 
-pub async fn add_order(payload: Json<Order>, pool: Data<PgPool>) -> impl Responder {
+pub async fn add_order(
+    user: AuthenticatedUser,
+    payload: ValidatedJson<Order>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
     let body = payload.into_inner();
     let description = body.description;
-    let res = match OrderRepo::create_order(description, &pool).await {
-        Ok(res) => res,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occured !! {:?}", e),
-                results: None,
-            });
-        }
-    };
-    HttpResponse::Ok().json(ApiResponse::<String> {
+    let res = OrderRepo::create_order(description, user.user_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
         status: 200,
         msg: format!("Order created !!\nRows Effected :: {}", res),
         results: None,
-    })
+    }))
+}
+
+#[cfg(test)]
+mod tests_orders {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+
+    #[actix_web::test]
+    async fn missing_orders_are_404_naming_the_order() {
+        let resp = order_not_found(&999).error_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let body: ApiResponse<String> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.status, 404);
+        assert_eq!(body.msg, "Order ID :: 999 not found !!");
+    }
 }
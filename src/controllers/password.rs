@@ -0,0 +1,160 @@
+use actix_web::web::Data;
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::repository::password_reset_repo::PasswordResetRepo;
+use crate::repository::user_repo::UserRepo;
+use crate::utils::config::AppConfig;
+use crate::utils::errors::AppError;
+use crate::utils::hashing_pool::HashingPool;
+use crate::utils::helpers::{random_token, sha256_hex};
+use crate::utils::keystore::KeyStore;
+use crate::utils::mailer::{send_in_background, Mail, Mailer};
+use crate::utils::revocation::RevocationStore;
+use crate::utils::throttle::{client_ip, LoginThrottle, MailThrottle};
+use crate::utils::types::{AuthenticatedUser, ChangePassword, ForgotPassword, ResetPassword};
+use crate::utils::validation::ValidatedJson;
+
+use super::api_responses::ApiResponse;
+use super::session::{end_all_sessions, load_session_roles, session_response, start_session};
+
+/// Checks the caller's current password, counting failures towards the same lockouts as
+/// `user_login` so a session cannot be used to guess it without limit.
+pub(crate) async fn verify_current_password(
+    user_id: i32,
+    sec: String,
+    req: &HttpRequest,
+    pool: &Data<PgPool>,
+    config: &AppConfig,
+    hasher: &HashingPool,
+) -> Result<(), AppError> {
+    let (user_login, current) = UserRepo::fetch_credentials(user_id, pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(String::from("User not found !!")))?;
+    let client_ip = client_ip(req);
+    LoginThrottle::check(&user_login, &client_ip, pool).await?;
+    if !hasher.verify_login(Some(current), sec).await? {
+        LoginThrottle::record_failure(&user_login, &client_ip, config, pool).await?;
+        return Err(AppError::Unauthorized(String::from(
+            "Current password is incorrect !!",
+        )));
+    }
+    LoginThrottle::record_success(&user_login, pool).await
+}
+
+/// Changes the caller's password after checking the current one.
+///
+/// Every existing session and API key of the user, including the credential making the
+/// request, is ended; the response carries a fresh session in the same shape as `user_login`.
+#[allow(clippy::too_many_arguments)]
+pub async fn change_password(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    payload: ValidatedJson<ChangePassword>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    keys: Data<KeyStore>,
+    hasher: Data<HashingPool>,
+    revocations: Data<RevocationStore>,
+) -> Result<HttpResponse, AppError> {
+    let payload = payload.into_inner();
+    verify_current_password(user.user_id, payload.old_sec, &req, &pool, &config, &hasher).await?;
+
+    let hash = hasher.hash(payload.new_sec).await?;
+    UserRepo::update_password_hash(user.user_id, &hash, &pool).await?;
+    end_all_sessions(user.user_id, &pool, &revocations).await?;
+
+    let roles = load_session_roles(user.user_id, &pool).await?;
+    let tokens = start_session(user.user_id, &roles, &pool, &config, &keys).await?;
+    Ok(session_response(&tokens, &config).json(ApiResponse {
+        status: 200,
+        msg: String::from("Password changed !!"),
+        results: Some(tokens),
+    }))
+}
+
+fn reset_mail(to: String, token: &str, config: &AppConfig) -> Mail {
+    Mail {
+        to,
+        subject: String::from("Reset your password"),
+        body: format!(
+            "Use the link below to choose a new password. It expires in {} minutes.\n\n{}?token={}",
+            config.password_reset_ttl.num_minutes(),
+            config.password_reset_url,
+            token
+        ),
+    }
+}
+
+/// Mails a single-use reset link to the account, if there is one.
+///
+/// The response is identical whether or not the login exists, and the mail is sent in the
+/// background with failures only logged, so this endpoint cannot be used to discover accounts.
+/// Requests are capped per login and per client IP by `MailThrottle` (HTTP 429).
+pub async fn forgot_password(
+    req: HttpRequest,
+    payload: ValidatedJson<ForgotPassword>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    mailer: Data<dyn Mailer>,
+) -> Result<HttpResponse, AppError> {
+    let payload = payload.into_inner();
+    MailThrottle::hit(&payload.user_login, &client_ip(&req), &config, &pool).await?;
+    if let Some((user_id, email)) = UserRepo::fetch_contact(&payload.user_login, &pool).await? {
+        let token = random_token(32);
+        let expires_at = (Utc::now() + config.password_reset_ttl).naive_utc();
+        PasswordResetRepo::create_token(user_id, &sha256_hex(&token), expires_at, &pool).await?;
+        send_in_background(&mailer, reset_mail(email, &token, &config));
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
+        status: 200,
+        msg: String::from("If the account exists, a reset link has been sent !!"),
+        results: None,
+    }))
+}
+
+/// Sets a new password with a token from `forgot_password` and ends every session of the user,
+/// revoking their API keys too, so a reset after a compromise locks every credential out.
+pub async fn reset_password(
+    payload: ValidatedJson<ResetPassword>,
+    pool: Data<PgPool>,
+    hasher: Data<HashingPool>,
+    revocations: Data<RevocationStore>,
+) -> Result<HttpResponse, AppError> {
+    let payload = payload.into_inner();
+    let now = Utc::now().naive_utc();
+    let user_id = PasswordResetRepo::consume_token(&sha256_hex(&payload.token), now, &pool)
+        .await?
+        .ok_or_else(|| AppError::Validation(String::from("Invalid or expired reset token !!")))?;
+
+    let hash = hasher.hash(payload.new_sec).await?;
+    UserRepo::update_password_hash(user_id, &hash, &pool).await?;
+    PasswordResetRepo::invalidate_for_user(user_id, now, &pool).await?;
+    end_all_sessions(user_id, &pool, &revocations).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
+        status: 200,
+        msg: String::from("Password reset, please log in again !!"),
+        results: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests_password {
+    use super::*;
+
+    #[test]
+    fn reset_mail_links_the_token_and_states_the_lifetime() {
+        let config = AppConfig {
+            password_reset_url: String::from("https://app.example/reset"),
+            password_reset_ttl: chrono::Duration::minutes(45),
+            ..AppConfig::default()
+        };
+        let mail = reset_mail(String::from("jane@example.com"), "abc123", &config);
+        assert_eq!(mail.to, "jane@example.com");
+        assert!(mail.body.contains("https://app.example/reset?token=abc123"));
+        assert!(mail.body.contains("45 minutes"));
+    }
+}
@@ -0,0 +1,94 @@
+use actix_web::web::{Data, Path};
+use actix_web::HttpResponse;
+use sqlx::PgPool;
+
+use crate::repository::user_repo::UserRepo;
+use crate::utils::errors::AppError;
+use crate::utils::revocation::RevocationStore;
+use crate::utils::types::{AuthenticatedUser, UpdateProfile, UserPath, UserProfile};
+use crate::utils::validation::ValidatedJson;
+
+use super::api_responses::ApiResponse;
+use super::session::end_all_sessions;
+
+/// Deactivated users are reported exactly like missing ones.
+fn user_not_found(user_id: i32) -> AppError {
+    AppError::NotFound(format!("User ID :: {} not found !!", user_id))
+}
+
+fn profile_response(msg: &str, profile: UserProfile) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: msg.to_string(),
+        results: Some(profile),
+    })
+}
+
+pub async fn get_my_profile(
+    user: AuthenticatedUser,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let profile = UserRepo::fetch_profile(user.user_id, &pool)
+        .await?
+        .ok_or_else(|| user_not_found(user.user_id))?;
+    Ok(profile_response("Profile fetched !!", profile))
+}
+
+pub async fn update_my_profile(
+    user: AuthenticatedUser,
+    payload: ValidatedJson<UpdateProfile>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let profile = UserRepo::update_profile(user.user_id, &payload, &pool)
+        .await?
+        .ok_or_else(|| user_not_found(user.user_id))?;
+    Ok(profile_response("Profile updated !!", profile))
+}
+
+pub async fn get_user_profile(
+    path: Path<UserPath>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let profile = UserRepo::fetch_profile(path.user_id, &pool)
+        .await?
+        .ok_or_else(|| user_not_found(path.user_id))?;
+    Ok(profile_response("Profile fetched !!", profile))
+}
+
+pub async fn update_user_profile(
+    path: Path<UserPath>,
+    payload: ValidatedJson<UpdateProfile>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let profile = UserRepo::update_profile(path.user_id, &payload, &pool)
+        .await?
+        .ok_or_else(|| user_not_found(path.user_id))?;
+    Ok(profile_response("Profile updated !!", profile))
+}
+
+/// Soft-deletes a user: `is_active` is cleared, like removed orders, and every session of the
+/// user is ended so existing tokens and API keys stop working at once. Admins cannot
+/// deactivate themselves.
+pub async fn deactivate_user(
+    admin: AuthenticatedUser,
+    path: Path<UserPath>,
+    pool: Data<PgPool>,
+    revocations: Data<RevocationStore>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.user_id;
+    if user_id == admin.user_id {
+        return Err(AppError::Validation(String::from(
+            "Cannot deactivate your own account !!",
+        )));
+    }
+    if UserRepo::deactivate_user(user_id, &pool).await? == 0 {
+        return Err(user_not_found(user_id));
+    }
+    end_all_sessions(user_id, &pool, &revocations).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
+        status: 200,
+        msg: format!("User ID :: {} deactivated !!", user_id),
+        results: None,
+    }))
+}
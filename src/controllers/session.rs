@@ -0,0 +1,225 @@
+use actix_web::web::{Data, Json};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middlewares::auth::verify_csrf;
+use crate::repository::api_key_repo::ApiKeyRepo;
+use crate::repository::refresh_token_repo::RefreshTokenRepo;
+use crate::repository::user_repo::UserRepo;
+use crate::utils::config::AppConfig;
+use crate::utils::constants::{ADMIN_ROLE, CSRF_HEADER, REFRESH_COOKIE_NAME};
+use crate::utils::errors::AppError;
+use crate::utils::helpers::{
+    build_auth_cookie, build_csrf_cookie, build_refresh_cookie, random_token, sha256_hex,
+};
+use crate::utils::jwt_impl::generate_jwt_token;
+use crate::utils::keystore::KeyStore;
+use crate::utils::revocation::RevocationStore;
+use crate::utils::types::{AuthenticatedUser, RefreshOutcome, RefreshRequest, SessionTokens};
+
+use super::api_responses::ApiResponse;
+
+/// Roles a session may carry. Admin rights require a second factor, so an admin without TOTP
+/// gets a session without `ADMIN_ROLE` until they enroll and log in again.
+pub fn session_roles(roles: Vec<String>, totp_enabled: bool) -> Vec<String> {
+    if totp_enabled {
+        return roles;
+    }
+    roles
+        .into_iter()
+        .filter(|role| role != ADMIN_ROLE)
+        .collect()
+}
+
+/// Current session roles of `user_id`, read fresh from the database; see `session_roles`.
+pub async fn load_session_roles(
+    user_id: i32,
+    pool: &Data<PgPool>,
+) -> Result<Vec<String>, AppError> {
+    let (roles, totp_enabled) = UserRepo::fetch_roles(user_id, pool).await?;
+    Ok(session_roles(roles, totp_enabled))
+}
+
+/// Mints an access token and starts a new refresh-token family for `user_id`.
+pub async fn start_session(
+    user_id: i32,
+    roles: &[String],
+    pool: &Data<PgPool>,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<SessionTokens, AppError> {
+    let refresh_token = random_token(32);
+    let expires_at = (Utc::now() + config.refresh_token_ttl).naive_utc();
+    RefreshTokenRepo::create_token(
+        user_id,
+        Uuid::new_v4(),
+        &sha256_hex(&refresh_token),
+        expires_at,
+        pool,
+    )
+    .await?;
+    session_tokens(user_id, roles, refresh_token, config, keys)
+}
+
+fn session_tokens(
+    user_id: i32,
+    roles: &[String],
+    refresh_token: String,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<SessionTokens, AppError> {
+    Ok(SessionTokens {
+        token_type: String::from("Bearer"),
+        access_token: generate_jwt_token(user_id, roles, config, keys)?,
+        refresh_token,
+        expires_in: config.access_token_ttl.num_seconds(),
+    })
+}
+
+/// Starts a 200 response carrying the session cookies and a fresh CSRF token.
+///
+/// The access cookie lives exactly as long as the access token, and the refresh cookie as long
+/// as the refresh token. The CSRF token is set as a readable cookie and also returned in
+/// `CSRF_HEADER`, so cross-origin clients that cannot read our cookies can still echo it back.
+pub fn session_response(tokens: &SessionTokens, config: &AppConfig) -> HttpResponseBuilder {
+    let csrf_token = random_token(32);
+    let mut builder = HttpResponse::Ok();
+    builder
+        .cookie(build_auth_cookie(
+            format!("Bearer {}", tokens.access_token),
+            config.access_token_ttl,
+        ))
+        .cookie(build_refresh_cookie(
+            tokens.refresh_token.clone(),
+            config.refresh_token_ttl,
+        ))
+        .cookie(build_csrf_cookie(
+            csrf_token.clone(),
+            config.refresh_token_ttl,
+        ))
+        .insert_header((CSRF_HEADER, csrf_token));
+    builder
+}
+
+/// Ends every session of `user_id`: all refresh tokens and API keys are revoked and every
+/// access token issued so far is rejected.
+pub async fn end_all_sessions(
+    user_id: i32,
+    pool: &Data<PgPool>,
+    revocations: &RevocationStore,
+) -> Result<(), AppError> {
+    RefreshTokenRepo::revoke_user(user_id, pool).await?;
+    ApiKeyRepo::revoke_user(user_id, pool).await?;
+    revocations.revoke_user(user_id, pool).await
+}
+
+/// Exchanges a refresh token (cookie first, then JSON body) for a new token pair.
+///
+/// Every refresh token is single use; presenting one that was already rotated revokes its
+/// whole family and answers 401. Roles are re-read from the database, so role changes take
+/// effect on the next refresh; see `load_session_roles`.
+pub async fn refresh_session(
+    req: HttpRequest,
+    payload: Option<Json<RefreshRequest>>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    keys: Data<KeyStore>,
+) -> Result<HttpResponse, AppError> {
+    let presented = presented_refresh_token(&req, payload)?
+        .ok_or_else(|| AppError::Unauthorized(String::from("Refresh token missing !!")))?;
+
+    let next_token = random_token(32);
+    let expires_at = (Utc::now() + config.refresh_token_ttl).naive_utc();
+    let outcome = RefreshTokenRepo::rotate_token(
+        &sha256_hex(&presented),
+        &sha256_hex(&next_token),
+        expires_at,
+        &pool,
+    )
+    .await?;
+
+    let user_id = match outcome {
+        RefreshOutcome::Rotated { user_id } => user_id,
+        RefreshOutcome::Reused | RefreshOutcome::Invalid => {
+            return Err(AppError::Unauthorized(String::from(
+                "Invalid refresh token !!",
+            )));
+        }
+    };
+
+    let roles = load_session_roles(user_id, &pool).await?;
+    let tokens = session_tokens(user_id, &roles, next_token, &config, &keys)?;
+    Ok(session_response(&tokens, &config).json(ApiResponse {
+        status: 200,
+        msg: String::from("Session refreshed !!"),
+        results: Some(tokens),
+    }))
+}
+
+/// Ends the caller's session: revokes the access token's `jti`, revokes the refresh-token
+/// family if a refresh token is presented, and clears all session cookies.
+///
+/// Works without a valid access token so a client holding only a refresh token can still
+/// log out. Any other failure, such as a cookie sent without the CSRF header, is returned
+/// rather than reported as a logout that left the token usable.
+pub async fn logout(
+    req: HttpRequest,
+    user: Result<AuthenticatedUser, AppError>,
+    payload: Option<Json<RefreshRequest>>,
+    pool: Data<PgPool>,
+    revocations: Data<RevocationStore>,
+) -> Result<HttpResponse, AppError> {
+    match user {
+        Ok(AuthenticatedUser {
+            token_id: Some(jti),
+            expires_at,
+            ..
+        }) => revocations.revoke(&jti, expires_at, &pool).await?,
+        Ok(_) | Err(AppError::Unauthorized(_)) => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(refresh_token) = presented_refresh_token(&req, payload)? {
+        RefreshTokenRepo::revoke_family(&sha256_hex(&refresh_token), &pool).await?;
+    }
+
+    let mut auth_cookie = build_auth_cookie(String::new(), Duration::zero());
+    auth_cookie.make_removal();
+    let mut refresh_cookie = build_refresh_cookie(String::new(), Duration::zero());
+    refresh_cookie.make_removal();
+    let mut csrf_cookie = build_csrf_cookie(String::new(), Duration::zero());
+    csrf_cookie.make_removal();
+
+    Ok(HttpResponse::Ok()
+        .cookie(auth_cookie)
+        .cookie(refresh_cookie)
+        .cookie(csrf_cookie)
+        .json(ApiResponse::<String> {
+            status: 200,
+            msg: String::from("Logged out !!"),
+            results: None,
+        }))
+}
+
+/// The refresh cookie is sent cross-site, so using it needs the same CSRF proof as the auth
+/// cookie. A token in the body is never sent by the browser on its own and needs none.
+fn presented_refresh_token(
+    req: &HttpRequest,
+    payload: Option<Json<RefreshRequest>>,
+) -> Result<Option<String>, AppError> {
+    if let Some(cookie) = req.cookie(REFRESH_COOKIE_NAME) {
+        if !cookie.value().is_empty() {
+            verify_csrf(req)?;
+            return Ok(Some(cookie.value().to_string()));
+        }
+    }
+    Ok(payload
+        .map(|body| body.into_inner().refresh_token)
+        .filter(|token| !token.is_empty()))
+}
+
+/// Publishes the public signing keys so other services can verify our tokens.
+pub async fn jwks(keys: Data<KeyStore>) -> HttpResponse {
+    HttpResponse::Ok().json(keys.jwks())
+}
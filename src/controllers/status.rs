@@ -1,55 +1,37 @@
-use std::sync::Arc;
-
-use actix_web::web::{Data, Json};
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder};
+use actix_web::web::Data;
+use actix_web::{HttpResponse, Responder};
 use sqlx::PgPool;
 
-use crate::utils::types::{UserDetail, UserInfo, UserPayload};
+use crate::utils::types::{AuthenticatedUser, UserDetail, UserInfo, UserPayload};
+use crate::utils::validation::ValidatedJson;
 
 use super::api_responses::ApiResponse;
 
-pub async fn check_user(req: HttpRequest, _pool: Data<PgPool>) -> impl Responder {
-    let extentions = req.extensions();
-    if let Some(user_details) = extentions.get::<Arc<UserInfo>>() {
-        HttpResponse::Ok().json(ApiResponse {
-            status: 200,
-            msg: "User info fetched!!".to_string(),
-            results: Some(user_details),
-        })
-    } else {
-        HttpResponse::Unauthorized().json(ApiResponse::<String> {
-            status: 401,
-            msg: "Unauthorized Access!!".to_string(),
-            results: None,
-        })
-    }
+pub async fn check_user(user: AuthenticatedUser, _pool: Data<PgPool>) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: "User info fetched!!".to_string(),
+        results: Some(UserInfo {
+            user_id: user.user_id,
+        }),
+    })
 }
 
 pub async fn save_user_test(
-    req: HttpRequest,
+    user: AuthenticatedUser,
     _pool: Data<PgPool>,
-    payload: Json<UserPayload>,
+    payload: ValidatedJson<UserPayload>,
 ) -> impl Responder {
     let payload = payload.into_inner();
-    let extentions = req.extensions();
-
-    if let Some(user_details) = extentions.get::<Arc<UserInfo>>() {
-        let user_item = UserDetail {
-            user_info: UserInfo {
-                user_id: user_details.user_id,
-            },
-            user_payload: payload,
-        };
-        HttpResponse::Ok().json(ApiResponse {
-            status: 200,
-            msg: "All is well !!".to_string(),
-            results: Some(user_item),
-        })
-    } else {
-        HttpResponse::Unauthorized().json(ApiResponse::<String> {
-            status: 401,
-            msg: "Unauthorized Access!!".to_string(),
-            results: None,
-        })
-    }
+    let user_item = UserDetail {
+        user_info: UserInfo {
+            user_id: user.user_id,
+        },
+        user_payload: payload,
+    };
+    HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: "All is well !!".to_string(),
+        results: Some(user_item),
+    })
 }
@@ -0,0 +1,148 @@
+use actix_web::web::{Data, Path};
+use actix_web::HttpResponse;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+use crate::repository::api_key_repo::ApiKeyRepo;
+use crate::utils::config::AppConfig;
+use crate::utils::constants::ALL_SCOPES;
+use crate::utils::errors::AppError;
+use crate::utils::helpers::{generate_api_key, sha256_hex};
+use crate::utils::jwt_impl::generate_personal_token;
+use crate::utils::keystore::KeyStore;
+use crate::utils::types::{
+    ApiKeyCreated, ApiKeyPath, ApiKeyRequest, AuthenticatedUser, PersonalToken,
+    PersonalTokenRequest,
+};
+use crate::utils::validation::ValidatedJson;
+
+use super::api_responses::ApiResponse;
+
+const DEFAULT_PERSONAL_TOKEN_DAYS: i64 = 30;
+const MAX_TOKEN_DAYS: i64 = 365;
+
+/// Callers can only hand out scopes they hold themselves, so a scoped credential cannot be
+/// used to mint a broader one.
+fn check_grantable_scopes(user: &AuthenticatedUser, scopes: &[String]) -> Result<(), AppError> {
+    if scopes.is_empty() {
+        return Err(AppError::Validation(String::from(
+            "At least one scope is required !!",
+        )));
+    }
+    if let Some(scope) = scopes
+        .iter()
+        .find(|scope| !ALL_SCOPES.contains(&scope.as_str()))
+    {
+        return Err(AppError::Validation(format!("Unknown scope {} !!", scope)));
+    }
+    if let Some(scope) = scopes.iter().find(|scope| !user.has_scope(scope)) {
+        return Err(AppError::Forbidden(format!(
+            "Cannot grant scope {} !!",
+            scope
+        )));
+    }
+    Ok(())
+}
+
+fn lifetime(days: i64) -> Result<Duration, AppError> {
+    if !(1..=MAX_TOKEN_DAYS).contains(&days) {
+        return Err(AppError::Validation(format!(
+            "expires_in_days must be between 1 and {} !!",
+            MAX_TOKEN_DAYS
+        )));
+    }
+    Ok(Duration::days(days))
+}
+
+/// Issues a personal access token limited to the requested scopes.
+///
+/// Personal tokens carry no roles and are revoked like any access token, by calling
+/// `/users/logout` with the token as bearer.
+pub async fn create_personal_token(
+    user: AuthenticatedUser,
+    payload: ValidatedJson<PersonalTokenRequest>,
+    config: Data<AppConfig>,
+    keys: Data<KeyStore>,
+) -> Result<HttpResponse, AppError> {
+    let payload = payload.into_inner();
+    check_grantable_scopes(&user, &payload.scopes)?;
+    let ttl = lifetime(
+        payload
+            .expires_in_days
+            .unwrap_or(DEFAULT_PERSONAL_TOKEN_DAYS),
+    )?;
+
+    let access_token = generate_personal_token(user.user_id, &payload.scopes, ttl, &config, &keys)?;
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: String::from("Token created !!"),
+        results: Some(PersonalToken {
+            token_type: String::from("Bearer"),
+            access_token,
+            scope: payload.scopes.join(" "),
+            expires_in: ttl.num_seconds(),
+        }),
+    }))
+}
+
+/// Creates an API key for service callers. The full key is only returned here.
+pub async fn create_api_key(
+    user: AuthenticatedUser,
+    payload: ValidatedJson<ApiKeyRequest>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let payload = payload.into_inner();
+    check_grantable_scopes(&user, &payload.scopes)?;
+    let expires_at = match payload.expires_in_days {
+        Some(days) => Some((Utc::now() + lifetime(days)?).naive_utc()),
+        None => None,
+    };
+
+    let (prefix, secret, key) = generate_api_key();
+    let info = ApiKeyRepo::create_key(
+        user.user_id,
+        payload.name.trim(),
+        &prefix,
+        &sha256_hex(&secret),
+        &payload.scopes,
+        expires_at,
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: String::from("API key created !!"),
+        results: Some(ApiKeyCreated { key, info }),
+    }))
+}
+
+pub async fn list_api_keys(
+    user: AuthenticatedUser,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let keys = ApiKeyRepo::list_keys(user.user_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        status: 200,
+        msg: format!("API keys fetched !! {} Records", keys.len()),
+        results: Some(keys),
+    }))
+}
+
+pub async fn revoke_api_key(
+    user: AuthenticatedUser,
+    path: Path<ApiKeyPath>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let key_id = path.key_id;
+    if ApiKeyRepo::revoke_key(key_id, user.user_id, &pool).await? == 0 {
+        return Err(AppError::NotFound(format!(
+            "API key :: {} not found !!",
+            key_id
+        )));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
+        status: 200,
+        msg: format!("API key :: {} revoked !!", key_id),
+        results: None,
+    }))
+}
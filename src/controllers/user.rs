@@ -1,182 +1,183 @@
-use actix_web::cookie::time::Duration;
-use actix_web::cookie::{Cookie, SameSite}; main
-use actix_web::web::{Data, Json};
-use actix_web::{HttpResponse, Responder};
+use actix_web::web::Data;
+use actix_web::{HttpRequest, HttpResponse};
+use log::error;
 use sqlx::PgPool;
 
 use crate::repository::user_repo::UserRepo;
-use crate::utils::helpers::build_auth_cookie;
-use crate::utils::jwt_impl::{generate_jwt_token, get_hash, validate_hash};
+use crate::utils::config::AppConfig;
+use crate::utils::errors::AppError;
+use crate::utils::hashing_pool::HashingPool;
+use crate::utils::helpers::sha256_hex;
+use crate::utils::keystore::KeyStore;
+use crate::utils::mailer::Mailer;
+use crate::utils::throttle::{client_ip, LoginThrottle};
 use crate::utils::types::{RegisterUser, UserLogin};
+use crate::utils::validation::ValidatedJson;
 
 use super::api_responses::ApiResponse;
+use super::mfa::mfa_challenge;
+use super::session::{session_response, session_roles, start_session};
+use super::verification::{mail_verification, new_verification_token};
 
-pub async fn fetch_all(pool: Data<PgPool>) -> impl Responder {
-    let user_list = match UserRepo::fetch_users_list(&pool).await {
-        Ok(res) => res,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occurred!! {:?}", e),
-                results: None,
-            });
-        }
-    };
-    HttpResponse::Ok().json(ApiResponse {
+/// Unknown logins and wrong passwords share one response so callers cannot tell them apart.
+fn invalid_credentials() -> AppError {
+    AppError::Unauthorized(String::from("Unauthorized Access !!!"))
+}
+
+pub async fn fetch_all(pool: Data<PgPool>) -> Result<HttpResponse, AppError> {
+    let user_list = UserRepo::fetch_users_list(&pool).await?;
+    Ok(HttpResponse::Ok().json(ApiResponse {
         status: 200,
         msg: "Users fetched !!".to_string(),
         results: Some(user_list),
-    })
+    }))
 }
 
-/// Register a new user, issue a JWT, and store it in a secure HTTP-only cookie.
+/// Register a new user and start a session for them.
 ///
 /// On success this handler sets a cookie named `"OKIJ"` containing `"Bearer <token>"` with
-/// SameSite=None, Secure, HttpOnly, Path="/" and a 2-hour max age, and returns HTTP 200 with
-/// an `ApiResponse` whose `msg` is `"User registered & token generated"`. If user registration
-/// or token generation fails the handler responds with HTTP 500 and an error message.
+/// SameSite=None, Secure, HttpOnly, Path="/" and a max age matching the access token, plus the
+/// refresh and CSRF cookies, and returns HTTP 200 with an `ApiResponse` whose `msg` is
+/// `"User registered & token generated"` and whose `results` hold the `SessionTokens`.
+/// A verification link is mailed to `email` in every case. When `require_email_verification` is
+/// on, no session is started: the response carries no tokens and the user logs in once verified.
+/// Failures are returned as an `AppError`, which renders the matching status code and
+/// `ApiResponse` body; a `user_login` or email that is already registered yields 409.
 ///
 /// # Examples
 ///
 /// ```no_run
 /// use actix_web::test;
-/// use actix_web::web::Json;
+/// use crate::utils::validation::ValidatedJson;
 ///
 /// // Build a RegisterUser payload and a PgPool `Data` wrapper before calling.
-/// let payload = Json(RegisterUser { /* fill required fields */ });
+/// let payload = ValidatedJson(RegisterUser { /* fill required fields */ });
 /// let pool = /* Data<PgPool> instance */;
 ///
 /// // Call the handler (in a test runtime)
-/// let resp = test::block_on(register_user(payload, pool));
-/// assert!(resp.status().is_success());
+/// let resp = test::block_on(register_user(payload, pool, config, keys, hasher, mailer));
+/// assert!(resp.is_ok());
 /// ```
-pub async fn register_user(payload: Json<RegisterUser>, pool: Data<PgPool>) -> impl Responder {
-    let payload = payload.into_inner();
-    let sec = &payload.sec;
-    let hash = get_hash(sec).unwrap();
-    let user_res = match UserRepo::user_registration(payload, &pool, hash).await {
-        Ok(res) => res,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occurred!! {:?}", e),
-                results: None,
-            });
-        }
-    };
+pub async fn register_user(
+    payload: ValidatedJson<RegisterUser>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    keys: Data<KeyStore>,
+    hasher: Data<HashingPool>,
+    mailer: Data<dyn Mailer>,
+) -> Result<HttpResponse, AppError> {
+    let mut payload = payload.into_inner();
+    let hash = hasher.hash(std::mem::take(&mut payload.sec)).await?;
+    let email = payload.email.clone();
+    let (token, expires_at) = new_verification_token(&config);
+    let user_id =
+        UserRepo::user_registration(payload, &pool, hash, &sha256_hex(&token), expires_at).await?;
+    mail_verification(&email, &token, &config, &mailer);
 
-    let token = match generate_jwt_token(user_res) {
-        Ok(token) => "Bearer ".to_string() + &token,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occurred!! {:?}", e),
-                results: None,
-            });
-        }
-    };
-
-
-    let cookie = build_auth_cookie(token.clone());
-
-    let cookie = Cookie::build("OKIJ", &token)
-        .http_only(true)
-        .same_site(SameSite::None)
-        .secure(true)
-        .path("/")
-        .max_age(Duration::hours(2))
-        .finish(); main
-
-    HttpResponse::Ok()
-        .cookie(cookie)
-        .json(ApiResponse::<String> {
+    if config.require_email_verification {
+        return Ok(HttpResponse::Ok().json(ApiResponse::<String> {
             status: 200,
-            msg: String::from("User registered & token generated"),
+            msg: String::from("User registered, check your email to verify the account !!"),
             results: None,
-        })
+        }));
+    }
+
+    let tokens = start_session(user_id, &[], &pool, &config, &keys).await?;
+
+    Ok(session_response(&tokens, &config).json(ApiResponse {
+        status: 200,
+        msg: String::from("User registered & token generated"),
+        results: Some(tokens),
+    }))
 }
 
-/// Authenticate a user and, on success, set a JWT in an HTTP-only secure cookie.
+/// Authenticate a user and, on success, start a session.
 ///
-/// On success: returns HTTP 200 with a JSON ApiResponse and a cookie named `"OKIJ"` containing the JWT.
-/// The cookie is HttpOnly, SameSite=None, Secure, Path="/", and expires after 2 hours.
-/// If credentials are invalid: returns HTTP 401 with an unauthorized ApiResponse.
-/// On repository or validation errors: returns HTTP 500 with an error ApiResponse.
+/// On success: returns HTTP 200 with a JSON ApiResponse carrying the `SessionTokens` and a
+/// cookie named `"OKIJ"` containing the access token. The cookie is HttpOnly, SameSite=None,
+/// Secure, Path="/", and expires together with the access token; the refresh token is set in
+/// its own cookie scoped to `/api/v1/users`.
+/// If the login is unknown or deactivated, or the password is wrong: returns
+/// `AppError::Unauthorized` (HTTP 401).
+/// With `require_email_verification` on, a correct password on an unverified account returns
+/// `AppError::Forbidden` (HTTP 403).
+/// Repeated failures lock the login (HTTP 423) or throttle the client IP (HTTP 429), both with
+/// a `Retry-After` header; see `LoginThrottle`.
+/// A stored hash made with an outdated algorithm or parameters is replaced after a successful
+/// login; see `PasswordHasher::needs_rehash`. All hashing runs on the `HashingPool`.
+/// With TOTP enabled, a correct password starts no session: the response carries an
+/// `MfaChallenge` whose `mfa_token` must be sent to `verify_mfa` with a code.
+/// Without TOTP the session never carries `ADMIN_ROLE`; see `session_roles`.
+/// On repository or hashing errors: returns `AppError::Internal` (HTTP 500).
 ///
 /// # Examples
 ///
 /// ```no_run
-/// use actix_web::{test, web::Json};
+/// use actix_web::test;
 /// // Construct a `UserLogin` payload and call the handler in an integration-style test.
 /// // On successful credentials the response will include a cookie named "OKIJ".
 /// let req_payload = UserLogin { /* fields */ };
-/// let resp = test::block_on(user_login(Json(req_payload), /* pool */));
+/// let resp = test::block_on(user_login(/* req */, ValidatedJson(req_payload), /* pool */, /* config */, /* keys */, /* hasher */));
 /// // inspect resp for status and cookie
 /// ```
-pub async fn user_login(payload: Json<UserLogin>, pool: Data<PgPool>) -> impl Responder {
+pub async fn user_login(
+    req: HttpRequest,
+    payload: ValidatedJson<UserLogin>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    keys: Data<KeyStore>,
+    hasher: Data<HashingPool>,
+) -> Result<HttpResponse, AppError> {
     let payload = &payload.into_inner();
-    let user_details = match UserRepo::fetch_one_user(payload, &pool).await {
-        Ok(res) => res,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occurred!! {:?}", e),
-                results: None,
-            });
+    let client_ip = client_ip(&req);
+    LoginThrottle::check(&payload.user_login, &client_ip, &pool).await?;
+
+    // Always run exactly one password verification so response time and body are the same
+    // whether or not the login exists.
+    let user_details = UserRepo::fetch_one_user(payload, &pool).await?;
+    let verified = hasher
+        .verify_login(
+            user_details.as_ref().map(|u| u.sec.clone()),
+            payload.sec.clone(),
+        )
+        .await?;
+    let user_details = match user_details {
+        Some(user) if verified => user,
+        _ => {
+            LoginThrottle::record_failure(&payload.user_login, &client_ip, &config, &pool).await?;
+            return Err(invalid_credentials());
         }
     };
-    let is_valid = match validate_hash(user_details.sec, &payload.sec) {
-        Ok(valid) => valid,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                status: 500,
-                msg: format!("Error occurred!! {:?}", e),
-                results: None,
-            });
+    LoginThrottle::record_success(&payload.user_login, &pool).await?;
+    if config.require_email_verification && user_details.email_verified_at.is_none() {
+        return Err(AppError::Forbidden(String::from(
+            "Email not verified, request a new link through /users/resend_verification !!",
+        )));
+    }
+    if hasher.needs_rehash(&user_details.sec) {
+        let rehashed = hasher.hash(payload.sec.clone()).await?;
+        if let Err(e) = UserRepo::update_password_hash(user_details.id, &rehashed, &pool).await {
+            error!(
+                "Failed to upgrade password hash for {} :: {}",
+                user_details.id, e
+            );
         }
-    };
-    if is_valid {
-      
-        let token = match generate_jwt_token(user_details.id) {
-            Ok(token) => "Bearer ".to_string() + &token,
-            Err(e) => {
-                return HttpResponse::InternalServerError().json(ApiResponse::<String> {
-                    status: 500,
-                    msg: format!("Error occured !! {:?}", e),
-                    results: None,
-                });
-            }
-        };
-        
-        let cookie = build_auth_cookie(token.clone());
-        
-        HttpResponse::Ok().cookie(cookie).json(ApiResponse {
+    }
+
+    if user_details.totp_enabled {
+        return Ok(HttpResponse::Ok().json(ApiResponse {
             status: 200,
-            msg: "User Loggedin !!".to_string(),
-            results: Some(token),
-        })
+            msg: String::from("MFA code required !!"),
+            results: Some(mfa_challenge(user_details.id, &config, &keys)?),
+        }));
+    }
 
-        let token = generate_jwt_token(user_details.id).unwrap();
-        let cookie = Cookie::build("OKIJ", &token)
-            .http_only(true)
-            .same_site(SameSite::None)
-            .secure(true)
-            .path("/")
-            .max_age(Duration::hours(2))
-            .finish();
+    let roles = session_roles(user_details.roles, user_details.totp_enabled);
+    let tokens = start_session(user_details.id, &roles, &pool, &config, &keys).await?;
 
-        HttpResponse::Ok()
-            .cookie(cookie)
-            .json(ApiResponse::<String> {
-                status: 200,
-                msg: "User Loggedin !!".to_string(),
-                results: None,
-            })  main
-    } else {
-        HttpResponse::Unauthorized().json(ApiResponse::<String> {
-            status: 401,
-            msg: "Unauthorized Access !!!".to_string(),
-            results: None,
-        })
-    }
+    Ok(session_response(&tokens, &config).json(ApiResponse {
+        status: 200,
+        msg: "User Loggedin !!".to_string(),
+        results: Some(tokens),
+    }))
 }
@@ -0,0 +1,116 @@
+use actix_web::web::{Data, Query};
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::{NaiveDateTime, Utc};
+use sqlx::PgPool;
+
+use crate::repository::email_verification_repo::EmailVerificationRepo;
+use crate::utils::config::AppConfig;
+use crate::utils::errors::AppError;
+use crate::utils::helpers::{random_token, sha256_hex};
+use crate::utils::mailer::{send_in_background, Mail, Mailer};
+use crate::utils::throttle::{client_ip, MailThrottle};
+use crate::utils::types::{ResendVerification, VerifyEmail};
+use crate::utils::validation::ValidatedJson;
+
+use super::api_responses::ApiResponse;
+
+/// A fresh verification token and when it expires. Only its hash is stored.
+pub fn new_verification_token(config: &AppConfig) -> (String, NaiveDateTime) {
+    let expires_at = (Utc::now() + config.email_verification_ttl).naive_utc();
+    (random_token(32), expires_at)
+}
+
+/// Mails the verification link for `token` to `email` in the background. A failed delivery is
+/// only logged: the account exists either way and the caller should not be told otherwise.
+pub fn mail_verification(email: &str, token: &str, config: &AppConfig, mailer: &Data<dyn Mailer>) {
+    send_in_background(mailer, verification_mail(email, token, config));
+}
+
+fn verification_mail(email: &str, token: &str, config: &AppConfig) -> Mail {
+    Mail {
+        to: email.to_string(),
+        subject: String::from("Verify your email"),
+        body: format!(
+            "Open the link below to verify your email. It expires in {} hours.\n\n{}?token={}",
+            config.email_verification_ttl.num_hours(),
+            config.email_verification_url,
+            token
+        ),
+    }
+}
+
+/// Mails a new verification link, for links that were lost or expired before being used.
+///
+/// Like `forgot_password`, the response is the same whether or not the login exists or still
+/// needs verifying, and requests are capped per login and per client IP by `MailThrottle`.
+pub async fn resend_verification(
+    req: HttpRequest,
+    payload: ValidatedJson<ResendVerification>,
+    pool: Data<PgPool>,
+    config: Data<AppConfig>,
+    mailer: Data<dyn Mailer>,
+) -> Result<HttpResponse, AppError> {
+    let payload = payload.into_inner();
+    MailThrottle::hit(&payload.user_login, &client_ip(&req), &config, &pool).await?;
+    if let Some((user_id, email)) =
+        EmailVerificationRepo::fetch_unverified(&payload.user_login, &pool).await?
+    {
+        let (token, expires_at) = new_verification_token(&config);
+        EmailVerificationRepo::create_token(user_id, &sha256_hex(&token), expires_at, &pool)
+            .await?;
+        mail_verification(&email, &token, &config, &mailer);
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
+        status: 200,
+        msg: String::from("If the account needs verifying, a new link has been sent !!"),
+        results: None,
+    }))
+}
+
+pub async fn verify_email(
+    query: Query<VerifyEmail>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    EmailVerificationRepo::verify(&sha256_hex(&query.token), Utc::now().naive_utc(), &pool)
+        .await?
+        .ok_or_else(|| {
+            AppError::Validation(String::from("Invalid or expired verification token !!"))
+        })?;
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
+        status: 200,
+        msg: String::from("Email verified !!"),
+        results: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests_verification {
+    use super::*;
+
+    #[test]
+    fn verification_mail_links_the_token_and_states_the_lifetime() {
+        let config = AppConfig {
+            email_verification_url: String::from("https://api.example/verify"),
+            email_verification_ttl: chrono::Duration::hours(12),
+            ..AppConfig::default()
+        };
+        let mail = verification_mail("jane@example.com", "abc123", &config);
+        assert_eq!(mail.to, "jane@example.com");
+        assert!(mail
+            .body
+            .contains("https://api.example/verify?token=abc123"));
+        assert!(mail.body.contains("12 hours"));
+    }
+
+    #[test]
+    fn verification_tokens_are_random_and_expire_after_the_ttl() {
+        let config = AppConfig::default();
+        let before = Utc::now().naive_utc();
+        let (first, expires_at) = new_verification_token(&config);
+        let (second, _) = new_verification_token(&config);
+        assert_ne!(first, second);
+        assert!(expires_at >= before + config.email_verification_ttl);
+        assert!(expires_at <= Utc::now().naive_utc() + config.email_verification_ttl);
+    }
+}
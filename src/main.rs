@@ -1,16 +1,25 @@
 use actix_cors::Cors;
 use actix_web::middleware::from_fn;
-use actix_web::web::Data;
+use actix_web::web::{Data, JsonConfig, QueryConfig};
 use actix_web::{App, HttpServer};
 use dotenv::dotenv;
 
-use log::info;
+use log::{error, info};
 use sqlx::postgres::PgPoolOptions;
 use std::{env, io};
 
 use self::middlewares::auth::authenticate_request;
 use self::middlewares::logger::log_requests;
-use self::utils::helpers::get_conn_url;
+use self::repository::migrations::MigrationRepo;
+use self::utils::config::AppConfig;
+use self::utils::errors::AppError;
+use self::utils::hashing_pool::HashingPool;
+use self::utils::helpers::{env_flag, get_conn_url};
+use self::utils::keystore::KeyStore;
+use self::utils::mailer::{mailer_from_env, Mailer};
+use self::utils::oidc::OidcClient;
+use self::utils::password::PasswordHasher;
+use self::utils::revocation::RevocationStore;
 mod controllers;
 mod middlewares;
 mod repository;
@@ -22,6 +31,15 @@ async fn main() -> std::io::Result<()> {
     env_logger::init();
     dotenv().ok();
 
+    // `actix-crud migrate` applies pending migrations and exits without serving.
+    let command = env::args().nth(1);
+    match command.as_deref() {
+        None | Some("serve") | Some("migrate") => {}
+        Some(other) => {
+            return Err(io::Error::other(format!("Unknown command :: {}", other)));
+        }
+    }
+
     info!("Starting the server");
     let db_url = get_conn_url();
 
@@ -30,11 +48,20 @@ async fn main() -> std::io::Result<()> {
         .await
         .map_err(|e| {
             info!("Error in connecting to the Database {:?}", e);
-            io::Error::other(
-                format!("Failed to connect to database :: {:?}", e).as_str(),
-            )
+            io::Error::other(format!("Failed to connect to database :: {:?}", e).as_str())
         })?;
 
+    if command.as_deref() == Some("migrate") || !env_flag("SKIP_MIGRATIONS") {
+        info!("Running database migrations");
+        MigrationRepo::run(&db_pool)
+            .await
+            .map_err(|e| io::Error::other(format!("Failed to run migrations :: {}", e)))?;
+    }
+    if command.as_deref() == Some("migrate") {
+        info!("Migrations applied");
+        return Ok(());
+    }
+
     let host = env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
     let port = env::var("PORT")
         .unwrap_or_else(|_| "6002".to_string())
@@ -43,13 +70,69 @@ async fn main() -> std::io::Result<()> {
     let allowed_origin =
         env::var("ALLOWED_ORIGIN").unwrap_or_else(|_| "127.0.0.1:5173".to_string());
 
+    let app_config = Data::new(AppConfig::from_env());
+    let password_hasher = PasswordHasher::new(&app_config)
+        .map_err(|e| io::Error::other(format!("Failed to set up password hashing :: {}", e)))?;
+    let hasher = Data::new(HashingPool::new(
+        password_hasher,
+        app_config.password_hash_concurrency,
+    ));
+    let mailer: Data<dyn Mailer> = Data::from(mailer_from_env());
+    let oidc = OidcClient::from_config(&app_config)
+        .map_err(|e| io::Error::other(format!("Failed to set up SSO login :: {}", e)))?
+        .map(Data::new);
+    let keys = Data::new(
+        KeyStore::from_env()
+            .map_err(|e| io::Error::other(format!("Failed to load signing keys :: {}", e)))?,
+    );
+
+    let revocations = Data::new(RevocationStore::default());
+    let sync_pool = Data::new(db_pool.clone());
+    revocations
+        .sync(&sync_pool)
+        .await
+        .map_err(|e| io::Error::other(format!("Failed to load revoked tokens :: {}", e)))?;
+    let sync_store = revocations.clone();
+    let sync_interval = app_config.revocation_sync_interval;
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(sync_interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = sync_store.sync(&sync_pool).await {
+                error!("Failed to sync revoked tokens :: {}", e);
+            }
+        }
+    });
+
     let server = HttpServer::new(move || {
-        App::new()
-            .app_data(Data::new(db_pool.clone()))
+        let mut app = App::new();
+        if let Some(oidc) = &oidc {
+            app = app.app_data(oidc.clone());
+        }
+        app.app_data(Data::new(db_pool.clone()))
+            .app_data(app_config.clone())
+            .app_data(revocations.clone())
+            .app_data(keys.clone())
+            .app_data(hasher.clone())
+            .app_data(mailer.clone())
+            .app_data(
+                JsonConfig::default()
+                    .limit(64 * 1024)
+                    .error_handler(|err, _| {
+                        AppError::Validation(format!("Invalid payload :: {}", err)).into()
+                    }),
+            )
+            .app_data(QueryConfig::default().error_handler(|err, _| {
+                AppError::Validation(format!("Invalid query :: {}", err)).into()
+            }))
             .wrap(
                 Cors::default()
                     .allowed_origin(allowed_origin.as_str())
                     .allowed_headers(utils::constants::HEADERS)
+                    .allowed_header(utils::constants::CSRF_HEADER)
+                    .allowed_header(utils::constants::API_KEY_HEADER)
+                    .expose_headers([utils::constants::CSRF_HEADER])
                     .allowed_methods(utils::constants::METHODS)
                     .supports_credentials()
                     .max_age(3600),
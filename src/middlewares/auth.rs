@@ -1,45 +1,77 @@
-use std::env;
-use std::sync::Arc;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
 
 use actix_web::body::MessageBody;
-use actix_web::dev::{ServiceRequest, ServiceResponse};
-use actix_web::error::ErrorUnauthorized;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
 use actix_web::middleware::Next;
-use actix_web::{Error, HttpMessage};
-use dotenv::dotenv;
-use jsonwebtoken::{decode, DecodingKey, Validation};
+use actix_web::web::Data;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use chrono::{Duration, NaiveDateTime, Utc};
 use log::{error, info};
+use sqlx::PgPool;
 
-use crate::utils::types::Claims;
+use crate::repository::api_key_repo::ApiKeyRepo;
+use crate::utils::config::AppConfig;
+use crate::utils::constants::{API_KEY_HEADER, COOKIE_NAME, CSRF_COOKIE_NAME, CSRF_HEADER};
+use crate::utils::errors::{AppError, TokenError};
+use crate::utils::helpers::{constant_time_eq, parse_api_key, sha256_hex};
+use crate::utils::jwt_impl::decode_jwt_token;
+use crate::utils::keystore::KeyStore;
+use crate::utils::revocation::RevocationStore;
+use crate::utils::types::{ApiKeyRecord, AuthenticatedUser, Claims};
 
+/// `last_used_at` is only written when older than this, to keep busy keys from turning every
+/// request into an UPDATE.
+const API_KEY_TOUCH_INTERVAL_SECS: i64 = 60;
+
+/// Resolves the caller from the `Authorization` header, the `API_KEY_HEADER` header or, failing
+/// those, the auth cookie.
+///
+/// A malformed or invalid header is rejected outright. An invalid or revoked cookie is ignored
+/// so a stale browser session can still reach public routes such as login. A valid cookie
+/// identity is attached but only honoured where it is used: the route guards and the
+/// `AuthenticatedUser` extractor then require unsafe methods to echo the CSRF cookie in
+/// `CSRF_HEADER` (double-submit), see `verify_csrf`. Public routes ignore the cookie entirely.
 pub async fn authenticate_request(
     req: ServiceRequest,
     next: Next<impl MessageBody>,
 ) -> Result<ServiceResponse<impl MessageBody>, Error> {
-    dotenv().ok();
-    let header_value = match req.headers().get("Authorization") {
+    let header_value = match req.headers().get(header::AUTHORIZATION) {
         Some(value) => value.to_str().ok(),
         None => None,
     };
 
     if let Some(auth_header) = header_value {
-        if auth_header.contains("Bearer ") {
-            let token = auth_header.replace("Bearer ", "");
-            let decoding_key = env::var("ENCODING_KEY").expect("Key not found!!!");
-            match decode::<Claims>(
-                &token,
-                &DecodingKey::from_secret(decoding_key.as_ref()),
-                &Validation::new(jsonwebtoken::Algorithm::HS256),
-            ) {
-                Ok(decoded) => {
-                    req.extensions_mut().insert(Arc::new(decoded.claims.sub));
-                }
-                Err(e) => {
-                    return Err(ErrorUnauthorized(format!("Invalid Request !!! {:?}", e)));
-                }
+        let token = match auth_header.strip_prefix("Bearer ") {
+            Some(token) => token,
+            None => {
+                return Err(AppError::InvalidToken(TokenError::Malformed).into());
             }
-        } else {
-            return Err(ErrorUnauthorized("Invalid token !!"));
+        };
+        let claims = decode_token(&req, token)?;
+        if is_revoked(&req, &claims)? {
+            return Err(AppError::InvalidToken(TokenError::Revoked).into());
+        }
+        req.extensions_mut().insert(AuthenticatedUser::from(claims));
+    } else if let Some(api_key) = req.headers().get(API_KEY_HEADER) {
+        let api_key = api_key.to_str().map_err(|_| invalid_api_key())?;
+        let user = authenticate_api_key(&req, api_key).await?;
+        req.extensions_mut().insert(user);
+    } else if let Some(token) = token_from_cookie(&req) {
+        match decode_token(&req, &token) {
+            Ok(claims) if !is_revoked(&req, &claims)? => {
+                let mut extensions = req.extensions_mut();
+                extensions.insert(AuthenticatedUser::from(claims));
+                extensions.insert(CookieIdentity);
+            }
+            Ok(_) => {
+                info!("Ignoring revoked auth cookie");
+            }
+            Err(AppError::InvalidToken(_)) => {
+                info!("Ignoring invalid auth cookie");
+            }
+            Err(e) => return Err(e.into()),
         }
     }
 
@@ -56,3 +88,481 @@ pub async fn authenticate_request(
     };
     Ok(response)
 }
+
+fn decode_token(req: &ServiceRequest, token: &str) -> Result<Claims, AppError> {
+    let keys = req
+        .app_data::<Data<KeyStore>>()
+        .ok_or_else(|| AppError::Internal(String::from("KeyStore not configured")))?;
+    let config = req
+        .app_data::<Data<AppConfig>>()
+        .ok_or_else(|| AppError::Internal(String::from("AppConfig not configured")))?;
+    decode_jwt_token(token, config, keys).map_err(|e| {
+        info!("Rejected token :: {:?}", e);
+        AppError::InvalidToken(TokenError::from(e))
+    })
+}
+
+fn is_revoked(req: &ServiceRequest, claims: &Claims) -> Result<bool, AppError> {
+    let store = req
+        .app_data::<Data<RevocationStore>>()
+        .ok_or_else(|| AppError::Internal(String::from("RevocationStore not configured")))?;
+    Ok(store.is_revoked(&claims.jti) || store.is_cut_off(claims.sub, claims.iat))
+}
+
+/// Looks the key up by prefix and checks it with `verify_api_key`.
+///
+/// API keys carry no roles, only the scopes they were created with, and cannot be revoked
+/// through `/users/logout`, hence no `token_id`.
+async fn authenticate_api_key(
+    req: &ServiceRequest,
+    api_key: &str,
+) -> Result<AuthenticatedUser, AppError> {
+    let (prefix, secret) = parse_api_key(api_key).ok_or_else(invalid_api_key)?;
+    let pool = req
+        .app_data::<Data<PgPool>>()
+        .ok_or_else(|| AppError::Internal(String::from("PgPool not configured")))?;
+    let record = ApiKeyRepo::find_by_prefix(prefix, pool)
+        .await?
+        .ok_or_else(invalid_api_key)?;
+    let now = Utc::now().naive_utc();
+    let user = verify_api_key(&record, secret, now)?;
+    if record
+        .last_used_at
+        .is_none_or(|at| now - at >= Duration::seconds(API_KEY_TOUCH_INTERVAL_SECS))
+    {
+        ApiKeyRepo::touch(record.id, now, pool).await?;
+    }
+    Ok(user)
+}
+
+/// Accepts the key if the secret matches and it is neither revoked, expired nor owned by a
+/// deactivated user. All failures look the same to the caller.
+fn verify_api_key(
+    record: &ApiKeyRecord,
+    secret: &str,
+    now: NaiveDateTime,
+) -> Result<AuthenticatedUser, AppError> {
+    let usable = constant_time_eq(&record.secret_hash, &sha256_hex(secret))
+        && record.revoked_at.is_none()
+        && record.expires_at.is_none_or(|at| at > now)
+        && record.user_active;
+    if !usable {
+        return Err(invalid_api_key());
+    }
+    Ok(AuthenticatedUser {
+        user_id: record.user_id,
+        roles: Vec::new(),
+        scopes: record.scopes.clone(),
+        token_id: None,
+        expires_at: record
+            .expires_at
+            .map(|at| at.and_utc().timestamp())
+            .unwrap_or(i64::MAX),
+        session: false,
+    })
+}
+
+fn invalid_api_key() -> AppError {
+    AppError::Unauthorized(String::from("Invalid API key !!"))
+}
+
+/// The handlers store `"Bearer <token>"` in the cookie; the prefix may arrive percent-encoded.
+fn token_from_cookie(req: &ServiceRequest) -> Option<String> {
+    let cookie = req.cookie(COOKIE_NAME)?;
+    let value = cookie.value();
+    let token = value
+        .strip_prefix("Bearer ")
+        .or_else(|| value.strip_prefix("Bearer%20"))
+        .unwrap_or(value);
+    if token.is_empty() {
+        None
+    } else {
+        Some(token.to_string())
+    }
+}
+
+/// Marks an `AuthenticatedUser` that came from the auth cookie rather than a header.
+struct CookieIdentity;
+
+/// Identity attached by `authenticate_request`, enforcing the CSRF check when it came from the
+/// cookie.
+fn cookie_checked_user(req: &HttpRequest) -> Result<Option<AuthenticatedUser>, AppError> {
+    let (user, from_cookie) = {
+        let extensions = req.extensions();
+        match extensions.get::<AuthenticatedUser>() {
+            Some(user) => (user.clone(), extensions.get::<CookieIdentity>().is_some()),
+            None => return Ok(None),
+        }
+    };
+    if from_cookie {
+        verify_csrf(req)?;
+    }
+    Ok(Some(user))
+}
+
+/// Double-submit check for requests authenticated by a cookie: unsafe methods must echo the
+/// CSRF cookie in `CSRF_HEADER`.
+pub fn verify_csrf(req: &HttpRequest) -> Result<(), AppError> {
+    if matches!(
+        *req.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    ) {
+        return Ok(());
+    }
+    let cookie = req.cookie(CSRF_COOKIE_NAME);
+    let header = req
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok());
+    match (cookie, header) {
+        (Some(cookie), Some(header))
+            if !header.is_empty() && constant_time_eq(cookie.value(), header) =>
+        {
+            Ok(())
+        }
+        _ => Err(AppError::Forbidden(String::from(
+            "CSRF token missing or invalid !!",
+        ))),
+    }
+}
+
+/// Route-level guard for endpoints that must not be reached anonymously.
+///
+/// `authenticate_request` only validates a token when one is sent; wrapping a scope or resource
+/// with `from_fn(require_auth)` additionally rejects requests that carry no token at all.
+pub async fn require_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if cookie_checked_user(req.request())?.is_none() {
+        return Err(unauthorized().into());
+    }
+    next.call(req).await
+}
+
+/// Route-level guard for account-security endpoints: only interactive sessions get through.
+///
+/// Personal tokens and API keys get 403 even when they hold every scope, so a leaked
+/// automation credential cannot take over the account, e.g. by enrolling its own TOTP secret.
+pub async fn require_session(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    match cookie_checked_user(req.request())? {
+        Some(user) if user.session => {}
+        Some(_) => {
+            return Err(AppError::Forbidden(String::from("A login session is required !!")).into());
+        }
+        None => return Err(unauthorized().into()),
+    }
+    next.call(req).await
+}
+
+type GuardFuture<B> = Pin<Box<dyn Future<Output = Result<ServiceResponse<B>, Error>>>>;
+
+/// Route-level guard that only lets callers holding `role` through.
+///
+/// Use as `.wrap(from_fn(require_role(ADMIN_ROLE)))`. Anonymous callers get 401 and
+/// authenticated callers without the role get 403.
+pub fn require_role<B: MessageBody + 'static>(
+    role: &'static str,
+) -> impl Fn(ServiceRequest, Next<B>) -> GuardFuture<B> {
+    require(move |user| {
+        if user.has_role(role) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(String::from("Insufficient role !!")))
+        }
+    })
+}
+
+/// Route-level guard that only lets tokens granted `scope` through, with the same 401/403 split
+/// as `require_role`. Session tokens hold every scope; personal access tokens only their own.
+pub fn require_scope<B: MessageBody + 'static>(
+    scope: &'static str,
+) -> impl Fn(ServiceRequest, Next<B>) -> GuardFuture<B> {
+    require(move |user| {
+        if user.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(format!("Missing scope {} !!", scope)))
+        }
+    })
+}
+
+fn require<B, C>(check: C) -> impl Fn(ServiceRequest, Next<B>) -> GuardFuture<B>
+where
+    B: MessageBody + 'static,
+    C: Fn(&AuthenticatedUser) -> Result<(), AppError> + Copy + 'static,
+{
+    move |req, next| {
+        Box::pin(async move {
+            match cookie_checked_user(req.request())? {
+                Some(user) => check(&user)?,
+                None => return Err(unauthorized().into()),
+            }
+            next.call(req).await
+        })
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(cookie_checked_user(req).and_then(|user| user.ok_or_else(unauthorized)))
+    }
+}
+
+fn unauthorized() -> AppError {
+    AppError::Unauthorized(String::from("Unauthorized Access!!"))
+}
+
+#[cfg(test)]
+mod tests_auth {
+    use super::*;
+    use actix_web::cookie::Cookie;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+
+    use crate::status_of;
+    use crate::utils::constants::{ORDERS_READ, ORDERS_WRITE};
+    use crate::utils::jwt_impl::generate_jwt_token;
+    use crate::utils::test_support::test_keys;
+
+    async fn whoami(user: AuthenticatedUser) -> HttpResponse {
+        HttpResponse::Ok().body(user.user_id.to_string())
+    }
+
+    #[actix_web::test]
+    async fn cookie_token_authenticates_and_requires_csrf_for_unsafe_methods() {
+        let keys = test_keys();
+        let token = format!(
+            "Bearer {}",
+            generate_jwt_token(7, &[], &AppConfig::default(), &keys).unwrap()
+        );
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(AppConfig::default()))
+                .app_data(Data::new(RevocationStore::default()))
+                .app_data(Data::new(keys))
+                .wrap(from_fn(authenticate_request))
+                .route("/me", web::get().to(whoami))
+                .route("/me", web::post().to(whoami)),
+        )
+        .await;
+
+        let get = test::TestRequest::get()
+            .uri("/me")
+            .cookie(Cookie::new(COOKIE_NAME, token.clone()));
+        assert_eq!(status_of!(&app, get), StatusCode::OK);
+
+        let post_without_csrf = test::TestRequest::post()
+            .uri("/me")
+            .cookie(Cookie::new(COOKIE_NAME, token.clone()));
+        assert_eq!(status_of!(&app, post_without_csrf), StatusCode::FORBIDDEN);
+
+        let post_with_csrf = test::TestRequest::post()
+            .uri("/me")
+            .cookie(Cookie::new(COOKIE_NAME, token.clone()))
+            .cookie(Cookie::new(CSRF_COOKIE_NAME, "abc"))
+            .insert_header((CSRF_HEADER, "abc"));
+        assert_eq!(status_of!(&app, post_with_csrf), StatusCode::OK);
+
+        let header_auth = test::TestRequest::post()
+            .uri("/me")
+            .insert_header((header::AUTHORIZATION, token));
+        assert_eq!(status_of!(&app, header_auth), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn rejected_tokens_report_distinct_codes() {
+        let keys = test_keys();
+        let config = AppConfig::default();
+        let other_audience = AppConfig {
+            jwt_audience: String::from("someone-else"),
+            ..AppConfig::default()
+        };
+        let expired = AppConfig {
+            access_token_ttl: chrono::Duration::minutes(-5),
+            ..AppConfig::default()
+        };
+        let cases = [
+            (
+                generate_jwt_token(7, &[], &other_audience, &keys).unwrap(),
+                TokenError::InvalidAudience,
+            ),
+            (
+                generate_jwt_token(7, &[], &expired, &keys).unwrap(),
+                TokenError::Expired,
+            ),
+            (
+                generate_jwt_token(7, &[], &config, &KeyStore::hmac("test", b"other-key")).unwrap(),
+                TokenError::InvalidSignature,
+            ),
+        ];
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(config))
+                .app_data(Data::new(RevocationStore::default()))
+                .app_data(Data::new(keys))
+                .wrap(from_fn(authenticate_request))
+                .route("/me", web::get().to(whoami)),
+        )
+        .await;
+
+        for (token, expected) in cases {
+            let req = test::TestRequest::get()
+                .uri("/me")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .to_request();
+            let err = test::try_call_service(&app, req).await.err().unwrap();
+            let err = err.as_error::<AppError>().unwrap();
+            assert!(
+                matches!(err, AppError::InvalidToken(reason) if *reason == expected),
+                "{:?}",
+                err
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn require_role_distinguishes_anonymous_from_forbidden() {
+        let keys = test_keys();
+        let config = AppConfig::default();
+        let admin = generate_jwt_token(1, &[String::from("admin")], &config, &keys).unwrap();
+        let member = generate_jwt_token(2, &[], &config, &keys).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(config))
+                .app_data(Data::new(RevocationStore::default()))
+                .app_data(Data::new(keys))
+                .wrap(from_fn(authenticate_request))
+                .service(
+                    web::resource("/admin")
+                        .wrap(from_fn(require_role("admin")))
+                        .route(web::get().to(whoami)),
+                ),
+        )
+        .await;
+
+        let anonymous = test::TestRequest::get().uri("/admin");
+        assert_eq!(status_of!(&app, anonymous), StatusCode::UNAUTHORIZED);
+
+        let without_role = test::TestRequest::get()
+            .uri("/admin")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", member)));
+        assert_eq!(status_of!(&app, without_role), StatusCode::FORBIDDEN);
+
+        let with_role = test::TestRequest::get()
+            .uri("/admin")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", admin)));
+        assert_eq!(status_of!(&app, with_role), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn revoked_tokens_are_rejected() {
+        let keys = test_keys();
+        let config = AppConfig::default();
+        let revoked = generate_jwt_token(7, &[], &config, &keys).unwrap();
+        let live = generate_jwt_token(7, &[], &config, &keys).unwrap();
+        let claims = decode_jwt_token(&revoked, &config, &keys).unwrap();
+        let store = RevocationStore::default();
+        store.merge(vec![(claims.jti, claims.exp)], Vec::new(), 0);
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(config))
+                .app_data(Data::new(store))
+                .app_data(Data::new(keys))
+                .wrap(from_fn(authenticate_request))
+                .route("/me", web::get().to(whoami)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/me")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", revoked)))
+            .to_request();
+        let err = test::try_call_service(&app, req).await.err().unwrap();
+        assert!(matches!(
+            err.as_error::<AppError>(),
+            Some(AppError::InvalidToken(TokenError::Revoked))
+        ));
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let other = test::TestRequest::get()
+            .uri("/me")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", live)));
+        assert_eq!(status_of!(&app, other), StatusCode::OK);
+
+        let revoked_cookie = test::TestRequest::get()
+            .uri("/me")
+            .cookie(Cookie::new(COOKIE_NAME, format!("Bearer {}", revoked)));
+        assert_eq!(status_of!(&app, revoked_cookie), StatusCode::UNAUTHORIZED);
+    }
+
+    fn key_record(secret: &str) -> ApiKeyRecord {
+        ApiKeyRecord {
+            id: 1,
+            user_id: 7,
+            secret_hash: sha256_hex(secret),
+            scopes: vec![String::from(ORDERS_READ)],
+            expires_at: None,
+            last_used_at: None,
+            revoked_at: None,
+            user_active: true,
+        }
+    }
+
+    #[actix_web::test]
+    async fn api_keys_are_limited_to_their_scopes() {
+        let now = Utc::now().naive_utc();
+        let user = verify_api_key(&key_record("secret"), "secret", now).unwrap();
+        assert_eq!(user.user_id, 7);
+        assert!(user.has_scope(ORDERS_READ));
+        assert!(!user.has_scope(ORDERS_WRITE));
+        assert!(user.roles.is_empty());
+        assert!(!user.session);
+        assert_eq!(user.token_id, None);
+    }
+
+    #[actix_web::test]
+    async fn revoked_expired_and_orphaned_api_keys_are_rejected() {
+        let now = Utc::now().naive_utc();
+        let revoked = ApiKeyRecord {
+            revoked_at: Some(now - Duration::days(1)),
+            ..key_record("secret")
+        };
+        let expired = ApiKeyRecord {
+            expires_at: Some(now - Duration::seconds(1)),
+            ..key_record("secret")
+        };
+        let deactivated_owner = ApiKeyRecord {
+            user_active: false,
+            ..key_record("secret")
+        };
+        for record in [revoked, expired, deactivated_owner] {
+            assert!(matches!(
+                verify_api_key(&record, "secret", now),
+                Err(AppError::Unauthorized(_))
+            ));
+        }
+        assert!(verify_api_key(&key_record("secret"), "other", now).is_err());
+
+        let expiring = ApiKeyRecord {
+            expires_at: Some(now + Duration::days(1)),
+            ..key_record("secret")
+        };
+        let user = verify_api_key(&expiring, "secret", now).unwrap();
+        assert_eq!(
+            user.expires_at,
+            (now + Duration::days(1)).and_utc().timestamp()
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use actix_web::web::Data;
+use chrono::NaiveDateTime;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+
+use crate::utils::errors::AppError;
+use crate::utils::types::{ApiKeyInfo, ApiKeyRecord};
+
+pub struct ApiKeyRepo;
+
+impl ApiKeyRepo {
+    pub async fn create_key(
+        user_id: i32,
+        name: &str,
+        prefix: &str,
+        secret_hash: &str,
+        scopes: &[String],
+        expires_at: Option<NaiveDateTime>,
+        pool: &Data<PgPool>,
+    ) -> Result<ApiKeyInfo, AppError> {
+        let row: PgRow = sqlx::query(
+            r#"INSERT INTO api_keys (user_id, name, prefix, secret_hash, scopes, expires_at) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, name, prefix, scopes, expires_at, last_used_at, created_at"#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(prefix)
+        .bind(secret_hash)
+        .bind(scopes)
+        .bind(expires_at)
+        .fetch_one(pool.as_ref())
+        .await?;
+        Ok(key_info(row))
+    }
+
+    /// Keys that have not been revoked, newest first. Expired keys are still listed.
+    pub async fn list_keys(user_id: i32, pool: &Data<PgPool>) -> Result<Vec<ApiKeyInfo>, AppError> {
+        let rows: Vec<PgRow> = sqlx::query(
+            r#"SELECT id, name, prefix, scopes, expires_at, last_used_at, created_at FROM api_keys
+            WHERE user_id = $1 AND revoked_at IS NULL ORDER BY id DESC"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool.as_ref())
+        .await?;
+        Ok(rows.into_iter().map(key_info).collect())
+    }
+
+    pub async fn revoke_key(
+        key_id: i32,
+        user_id: i32,
+        pool: &Data<PgPool>,
+    ) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE api_keys SET revoked_at = now() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL"#,
+        )
+        .bind(key_id)
+        .bind(user_id)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Revokes every key of the user, e.g. when all their credentials must stop working.
+    pub async fn revoke_user(user_id: i32, pool: &Data<PgPool>) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE api_keys SET revoked_at = now() WHERE user_id = $1 AND revoked_at IS NULL"#,
+        )
+        .bind(user_id)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// The key with this prefix, revoked or expired ones included; the caller decides whether
+    /// it is usable.
+    pub async fn find_by_prefix(
+        prefix: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<ApiKeyRecord>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"SELECT k.id, k.user_id, k.secret_hash, k.scopes, k.expires_at, k.last_used_at,
+                k.revoked_at, u.is_active AS user_active
+            FROM api_keys k JOIN app_users u ON u.id = k.user_id
+            WHERE k.prefix = $1"#,
+        )
+        .bind(prefix)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(row.map(|row| ApiKeyRecord {
+            id: row.get("id"),
+            user_id: row.get("user_id"),
+            secret_hash: row.get("secret_hash"),
+            scopes: row.get("scopes"),
+            expires_at: row.get("expires_at"),
+            last_used_at: row.get("last_used_at"),
+            revoked_at: row.get("revoked_at"),
+            user_active: row.get("user_active"),
+        }))
+    }
+
+    pub async fn touch(
+        key_id: i32,
+        now: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query(r#"UPDATE api_keys SET last_used_at = $2 WHERE id = $1"#)
+            .bind(key_id)
+            .bind(now)
+            .execute(pool.as_ref())
+            .await?;
+        Ok(())
+    }
+}
+
+fn key_info(row: PgRow) -> ApiKeyInfo {
+    ApiKeyInfo {
+        id: row.get("id"),
+        name: row.get("name"),
+        prefix: row.get("prefix"),
+        scopes: row.get("scopes"),
+        expires_at: row.get("expires_at"),
+        last_used_at: row.get("last_used_at"),
+        created_at: row.get("created_at"),
+    }
+}
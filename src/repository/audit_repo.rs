@@ -0,0 +1,27 @@
+use actix_web::web::Data;
+use sqlx::PgPool;
+
+use crate::utils::errors::AppError;
+
+pub struct AuditRepo;
+
+impl AuditRepo {
+    pub async fn record(
+        event: &str,
+        user_login: Option<&str>,
+        client_ip: &str,
+        details: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"INSERT INTO audit_log (event, user_login, client_ip, details) VALUES ($1, $2, $3, $4)"#,
+        )
+        .bind(event)
+        .bind(user_login)
+        .bind(client_ip)
+        .bind(details)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use actix_web::web::Data;
+use chrono::NaiveDateTime;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+
+use crate::utils::errors::AppError;
+
+/// Timestamps are naive UTC and always bound from Rust, never SQL `now()`, which would follow
+/// the session time zone.
+pub struct EmailVerificationRepo;
+
+impl EmailVerificationRepo {
+    pub async fn create_token(
+        user_id: i32,
+        token_hash: &str,
+        expires_at: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"INSERT INTO email_verification_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3)"#,
+        )
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    /// Id and email of an active account with `user_login` whose email is not verified yet.
+    pub async fn fetch_unverified(
+        user_login: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<(i32, String)>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"SELECT id, email FROM app_users
+            WHERE user_login = $1 AND is_active = TRUE AND email IS NOT NULL
+            AND email_verified_at IS NULL"#,
+        )
+        .bind(user_login)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(row.map(|row| (row.get("id"), row.get("email"))))
+    }
+
+    /// Uses the token and marks its user's email verified in one transaction. Returns the user,
+    /// or `None` if the token is unknown, used or expired.
+    pub async fn verify(
+        token_hash: &str,
+        now: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<i32>, AppError> {
+        let mut tx = pool.begin().await?;
+        let user_id: Option<i32> = sqlx::query_scalar(
+            r#"UPDATE email_verification_tokens SET used_at = $2
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+            RETURNING user_id"#,
+        )
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(user_id) = user_id {
+            sqlx::query(
+                r#"UPDATE app_users SET email_verified_at = $2 WHERE id = $1 AND email_verified_at IS NULL"#,
+            )
+            .bind(user_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(user_id)
+    }
+}
@@ -1,13 +1,13 @@
-use std::error::Error;
-
 use actix_web::web::Data;
 use sqlx::postgres::PgRow;
 use sqlx::PgPool;
 
+use crate::utils::errors::AppError;
+
 pub struct HealthCheckRepo;
 
 impl HealthCheckRepo {
-    pub async fn check_status(pool: &Data<PgPool>) -> Result<String, Box<dyn Error>> {
+    pub async fn check_status(pool: &Data<PgPool>) -> Result<String, AppError> {
         let res: PgRow = sqlx::query("SELECT now() as current_time")
             .fetch_one(pool.as_ref())
             .await?;
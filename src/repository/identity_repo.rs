@@ -0,0 +1,87 @@
+use actix_web::web::Data;
+use log::warn;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+
+use crate::utils::errors::{is_unique_violation, AppError};
+use crate::utils::types::IdTokenClaims;
+
+pub struct IdentityRepo;
+
+impl IdentityRepo {
+    /// Local user linked to `issuer` + `subject` and whether it is still active, recording the
+    /// login on the way. `issuer` is the configured one rather than the token's `iss`, which may
+    /// differ by a trailing slash.
+    pub async fn find_user(
+        issuer: &str,
+        subject: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<(i32, bool)>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"UPDATE user_identities SET last_login_at = now()
+            WHERE issuer = $1 AND subject = $2
+            RETURNING user_id,
+                (SELECT is_active FROM app_users WHERE app_users.id = user_identities.user_id) AS is_active"#,
+        )
+        .bind(issuer)
+        .bind(subject)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(row.map(|row| (row.get("user_id"), row.get("is_active"))))
+    }
+
+    /// Creates a local user for a first SSO login and links the identity, in one transaction.
+    ///
+    /// The email is only stored when the provider vouches for it. An email that already
+    /// belongs to a local account is a 409: accounts are never linked by email alone. So is a
+    /// derived login that is already taken, which registration no longer allows but older
+    /// accounts may hold.
+    pub async fn provision(
+        issuer: &str,
+        claims: &IdTokenClaims,
+        user_name: &str,
+        user_login: &str,
+        sec_hash: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<i32, AppError> {
+        let email = claims.email.as_deref().filter(|_| claims.email_verified);
+        let mut tx = pool.begin().await?;
+        let user_id: i32 = sqlx::query_scalar(
+            r#"INSERT INTO app_users (user_name, sec, user_login, email, email_verified_at)
+            VALUES ($1, $2, $3, $4, now()) RETURNING id"#,
+        )
+        .bind(user_name)
+        .bind(sec_hash)
+        .bind(user_login)
+        .bind(email)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db_err) if is_unique_violation(&e) => match db_err.constraint() {
+                Some("uq_app_users_email") => {
+                    AppError::Conflict(String::from("Email already registered !!"))
+                }
+                Some("uq_app_users_user_login") => {
+                    warn!("SSO login {} is held by a local account", user_login);
+                    AppError::Conflict(String::from(
+                        "SSO login already taken by another account !!",
+                    ))
+                }
+                _ => e.into(),
+            },
+            _ => e.into(),
+        })?;
+        sqlx::query(
+            r#"INSERT INTO user_identities (user_id, issuer, subject, email, last_login_at)
+            VALUES ($1, $2, $3, $4, now())"#,
+        )
+        .bind(user_id)
+        .bind(issuer)
+        .bind(&claims.sub)
+        .bind(&claims.email)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(user_id)
+    }
+}
@@ -0,0 +1,80 @@
+use actix_web::web::Data;
+use chrono::{Duration, NaiveDateTime};
+use sqlx::PgPool;
+
+use crate::utils::errors::AppError;
+
+/// Timestamps are naive UTC and always bound from Rust, never SQL `now()`, which would follow
+/// the session time zone.
+pub struct LoginAttemptRepo;
+
+impl LoginAttemptRepo {
+    pub async fn locked_until(
+        scope: &str,
+        key: &str,
+        now: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<NaiveDateTime>, AppError> {
+        let until: Option<NaiveDateTime> = sqlx::query_scalar(
+            r#"SELECT locked_until FROM login_attempts WHERE scope = $1 AND key = $2 AND locked_until > $3"#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(now)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(until)
+    }
+
+    /// Counts one more failure and returns the running total. Failures older than
+    /// `window_secs` are forgotten, so the count restarts after a quiet period.
+    pub async fn record_failure(
+        scope: &str,
+        key: &str,
+        now: NaiveDateTime,
+        window_secs: i64,
+        pool: &Data<PgPool>,
+    ) -> Result<i32, AppError> {
+        let failures: i32 = sqlx::query_scalar(
+            r#"INSERT INTO login_attempts (scope, key, failures, last_failure_at) VALUES ($1, $2, 1, $3)
+            ON CONFLICT (scope, key) DO UPDATE SET
+                failures = CASE
+                    WHEN login_attempts.last_failure_at < $4 THEN 1
+                    ELSE login_attempts.failures + 1
+                END,
+                last_failure_at = $3
+            RETURNING failures"#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(now)
+        .bind(now - Duration::seconds(window_secs))
+        .fetch_one(pool.as_ref())
+        .await?;
+        Ok(failures)
+    }
+
+    pub async fn lock(
+        scope: &str,
+        key: &str,
+        until: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query(r#"UPDATE login_attempts SET locked_until = $3 WHERE scope = $1 AND key = $2"#)
+            .bind(scope)
+            .bind(key)
+            .bind(until)
+            .execute(pool.as_ref())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn clear(scope: &str, key: &str, pool: &Data<PgPool>) -> Result<(), AppError> {
+        sqlx::query(r#"DELETE FROM login_attempts WHERE scope = $1 AND key = $2"#)
+            .bind(scope)
+            .bind(key)
+            .execute(pool.as_ref())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use actix_web::web::Data;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+
+use crate::utils::errors::AppError;
+use crate::utils::types::MfaState;
+
+pub struct MfaRepo;
+
+impl MfaRepo {
+    pub async fn fetch_state(
+        user_id: i32,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<MfaState>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"SELECT user_login, roles, totp_secret, totp_enabled_at IS NOT NULL AS totp_enabled
+            FROM app_users WHERE id = $1 AND is_active = TRUE"#,
+        )
+        .bind(user_id)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(row.map(|row| MfaState {
+            user_login: row.get("user_login"),
+            roles: row.get("roles"),
+            totp_secret: row.get("totp_secret"),
+            totp_enabled: row.get("totp_enabled"),
+        }))
+    }
+
+    /// Stores a secret awaiting confirmation. Returns `false` if TOTP is already enabled.
+    pub async fn set_pending_secret(
+        user_id: i32,
+        secret: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE app_users SET totp_secret = $1, totp_last_step = NULL
+            WHERE id = $2 AND totp_enabled_at IS NULL"#,
+        )
+        .bind(secret)
+        .bind(user_id)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Turns TOTP on and replaces the recovery codes in one transaction. `step` is the step of
+    /// the confirming code, so it cannot be used again to log in.
+    pub async fn enable(
+        user_id: i32,
+        step: i64,
+        code_hashes: &[String],
+        pool: &Data<PgPool>,
+    ) -> Result<bool, AppError> {
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query(
+            r#"UPDATE app_users SET totp_enabled_at = now(), totp_last_step = $1
+            WHERE id = $2 AND totp_secret IS NOT NULL AND totp_enabled_at IS NULL"#,
+        )
+        .bind(step)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r#"INSERT INTO mfa_recovery_codes (user_id, code_hash) SELECT $1, unnest($2::TEXT[])"#,
+        )
+        .bind(user_id)
+        .bind(code_hashes)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Records `step` as used. Returns `false` if that step or a later one was already used.
+    pub async fn claim_step(
+        user_id: i32,
+        step: i64,
+        pool: &Data<PgPool>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE app_users SET totp_last_step = $1
+            WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)"#,
+        )
+        .bind(step)
+        .bind(user_id)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Burns the recovery code. Returns `false` if it is unknown or already used.
+    pub async fn use_recovery_code(
+        user_id: i32,
+        code_hash: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE mfa_recovery_codes SET used_at = now()
+            WHERE id = (
+                SELECT id FROM mfa_recovery_codes
+                WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                LIMIT 1
+            ) AND used_at IS NULL"#,
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
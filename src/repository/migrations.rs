@@ -0,0 +1,66 @@
+use sqlx::migrate::Migrator;
+use sqlx::PgPool;
+
+use crate::utils::errors::AppError;
+
+/// SQL migrations from `migrations/`, embedded into the binary at compile time.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+pub struct MigrationRepo;
+
+impl MigrationRepo {
+    pub async fn run(pool: &PgPool) -> Result<(), AppError> {
+        MIGRATOR
+            .run(pool)
+            .await
+            .map_err(|e| AppError::Internal(format!("{:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests_migrations {
+    use super::*;
+
+    #[test]
+    fn every_migration_file_is_embedded_in_order() {
+        let on_disk = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext == "sql")
+            })
+            .count();
+        let versions: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        assert_eq!(versions.len(), on_disk);
+        assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn every_table_the_repositories_use_is_created() {
+        let sql: String = MIGRATOR.iter().map(|m| m.sql.as_ref()).collect();
+        for table in [
+            "app_users",
+            "orders",
+            "refresh_tokens",
+            "revoked_tokens",
+            "token_cutoffs",
+            "api_keys",
+            "login_attempts",
+            "audit_log",
+            "password_reset_tokens",
+            "email_verification_tokens",
+            "mfa_recovery_codes",
+            "user_identities",
+        ] {
+            assert!(
+                sql.contains(&format!("CREATE TABLE IF NOT EXISTS {} (", table)),
+                "{}",
+                table
+            );
+        }
+    }
+}
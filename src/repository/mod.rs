@@ -1,3 +1,13 @@
+pub mod api_key_repo;
+pub mod audit_repo;
+pub mod email_verification_repo;
 pub mod health_check;
+pub mod identity_repo;
+pub mod login_attempt_repo;
+pub mod mfa_repo;
+pub mod migrations;
 pub mod order_repo;
+pub mod password_reset_repo;
+pub mod refresh_token_repo;
+pub mod revocation_repo;
 pub mod user_repo;
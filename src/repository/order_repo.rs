@@ -1,9 +1,8 @@
-use std::error::Error;
-
 use actix_web::web::Data;
 use sqlx::postgres::PgRow;
 use sqlx::{PgPool, Row};
 
+use crate::utils::errors::AppError;
 use crate::utils::types::OrderDetails;
 
 pub struct OrderRepo;
@@ -11,28 +10,35 @@ pub struct OrderRepo;
 impl OrderRepo {
     pub async fn get_one_order_detail(
         order_id: &i32,
+        user_id: i32,
         pool: &Data<PgPool>,
-    ) -> Result<OrderDetails, Box<dyn Error>> {
-        let row: PgRow = sqlx::query(
-            r#"SELECT order_id, description, created_at FROM orders WHERE order_id = $1 AND is_active = $2"#,
+    ) -> Result<Option<OrderDetails>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"SELECT order_id, description, created_at FROM orders WHERE order_id = $1 AND user_id = $2 AND is_active = $3"#,
         )
         .bind(order_id)
+        .bind(user_id)
         .bind(true)
-        .fetch_one(pool.as_ref())
+        .fetch_optional(pool.as_ref())
         .await?;
-        let order_details = OrderDetails {
+        let order_details = row.map(|row| OrderDetails {
             order_id: row.get("order_id"),
             description: row.get("description"),
             created_at: row.get("created_at"),
-        };
+        });
         Ok(order_details)
     }
 
-    pub async fn fetch_orders(pool: &Data<PgPool>) -> Result<Vec<OrderDetails>, Box<dyn Error>> {
-        let rows: Vec<PgRow> = sqlx::query(r#"SELECT * FROM orders WHERE is_active = $1"#)
-            .bind(true)
-            .fetch_all(pool.as_ref())
-            .await?;
+    pub async fn fetch_orders(
+        user_id: i32,
+        pool: &Data<PgPool>,
+    ) -> Result<Vec<OrderDetails>, AppError> {
+        let rows: Vec<PgRow> =
+            sqlx::query(r#"SELECT * FROM orders WHERE user_id = $1 AND is_active = $2"#)
+                .bind(user_id)
+                .bind(true)
+                .fetch_all(pool.as_ref())
+                .await?;
         let mut order_list = Vec::new();
         for row in rows {
             order_list.push(OrderDetails {
@@ -46,19 +52,29 @@ impl OrderRepo {
 
     pub async fn deactivate_order(
         order_id: &i32,
+        user_id: i32,
         pool: &Data<PgPool>,
-    ) -> Result<u64, Box<dyn Error>> {
-        let result = sqlx::query(r#"UPDATE orders SET is_active = $1 WHERE order_id = $2"#)
-            .bind(false)
-            .bind(order_id)
-            .execute(pool.as_ref())
-            .await?;
+    ) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE orders SET is_active = $1 WHERE order_id = $2 AND user_id = $3 AND is_active = $4"#,
+        )
+        .bind(false)
+        .bind(order_id)
+        .bind(user_id)
+        .bind(true)
+        .execute(pool.as_ref())
+        .await?;
         Ok(result.rows_affected())
     }
 
-    pub async fn create_order(desc: String, pool: &Data<PgPool>) -> Result<u64, Box<dyn Error>> {
-        let result = sqlx::query(r#"INSERT INTO orders (description) VALUES ($1)"#)
+    pub async fn create_order(
+        desc: String,
+        user_id: i32,
+        pool: &Data<PgPool>,
+    ) -> Result<u64, AppError> {
+        let result = sqlx::query(r#"INSERT INTO orders (description, user_id) VALUES ($1, $2)"#)
             .bind(desc)
+            .bind(user_id)
             .execute(pool.as_ref())
             .await?;
 
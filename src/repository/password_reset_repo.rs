@@ -0,0 +1,62 @@
+use actix_web::web::Data;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+use crate::utils::errors::AppError;
+
+/// Timestamps are naive UTC and always bound from Rust, never SQL `now()`, which would follow
+/// the session time zone.
+pub struct PasswordResetRepo;
+
+impl PasswordResetRepo {
+    pub async fn create_token(
+        user_id: i32,
+        token_hash: &str,
+        expires_at: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3)"#,
+        )
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    /// Marks the token used and returns its user, or `None` if it is unknown, used or expired.
+    pub async fn consume_token(
+        token_hash: &str,
+        now: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<i32>, AppError> {
+        let user_id: Option<i32> = sqlx::query_scalar(
+            r#"UPDATE password_reset_tokens SET used_at = $2
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+            RETURNING user_id"#,
+        )
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(user_id)
+    }
+
+    /// Burns every outstanding token of the user once one of them has been used.
+    pub async fn invalidate_for_user(
+        user_id: i32,
+        now: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE password_reset_tokens SET used_at = $2 WHERE user_id = $1 AND used_at IS NULL"#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,173 @@
+use actix_web::web::Data;
+use chrono::{NaiveDateTime, Utc};
+use log::warn;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::utils::errors::AppError;
+use crate::utils::types::RefreshOutcome;
+
+pub struct RefreshTokenRepo;
+
+impl RefreshTokenRepo {
+    pub async fn create_token(
+        user_id: i32,
+        family_id: Uuid,
+        token_hash: &str,
+        expires_at: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) VALUES ($1, $2, $3, $4)"#,
+        )
+        .bind(user_id)
+        .bind(family_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    /// Exchanges `token_hash` for `new_hash` within the same family.
+    ///
+    /// A token that was already used or revoked is treated as stolen: the whole family is
+    /// revoked so neither the attacker nor the victim can keep refreshing with it.
+    pub async fn rotate_token(
+        token_hash: &str,
+        new_hash: &str,
+        new_expires_at: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<RefreshOutcome, AppError> {
+        let mut tx = pool.begin().await?;
+        let row: Option<PgRow> = sqlx::query(
+            r#"SELECT id, user_id, family_id, expires_at, used_at, revoked_at FROM refresh_tokens WHERE token_hash = $1 FOR UPDATE"#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(row) = row else {
+            return Ok(RefreshOutcome::Invalid);
+        };
+
+        let id: i32 = row.get("id");
+        let user_id: i32 = row.get("user_id");
+        let family_id: Uuid = row.get("family_id");
+        let expires_at: NaiveDateTime = row.get("expires_at");
+        let used_at: Option<NaiveDateTime> = row.get("used_at");
+        let revoked_at: Option<NaiveDateTime> = row.get("revoked_at");
+
+        match stored_token_state(used_at, revoked_at, expires_at, Utc::now().naive_utc()) {
+            StoredToken::Live => {}
+            StoredToken::Expired => return Ok(RefreshOutcome::Invalid),
+            StoredToken::Spent => {
+                warn!(
+                    "Refresh token reuse detected for user {} :: revoking family {}",
+                    user_id, family_id
+                );
+                sqlx::query(
+                    r#"UPDATE refresh_tokens SET revoked_at = now() WHERE family_id = $1 AND revoked_at IS NULL"#,
+                )
+                .bind(family_id)
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                return Ok(RefreshOutcome::Reused);
+            }
+        }
+
+        sqlx::query(r#"UPDATE refresh_tokens SET used_at = now() WHERE id = $1"#)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r#"INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) VALUES ($1, $2, $3, $4)"#,
+        )
+        .bind(user_id)
+        .bind(family_id)
+        .bind(new_hash)
+        .bind(new_expires_at)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(RefreshOutcome::Rotated { user_id })
+    }
+
+    /// Revokes every token in the family `token_hash` belongs to; unknown hashes are ignored.
+    pub async fn revoke_family(token_hash: &str, pool: &Data<PgPool>) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE refresh_tokens SET revoked_at = now() WHERE revoked_at IS NULL AND family_id = (SELECT family_id FROM refresh_tokens WHERE token_hash = $1)"#,
+        )
+        .bind(token_hash)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Revokes every refresh token of the user, ending all of their sessions.
+    pub async fn revoke_user(user_id: i32, pool: &Data<PgPool>) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE refresh_tokens SET revoked_at = now() WHERE user_id = $1 AND revoked_at IS NULL"#,
+        )
+        .bind(user_id)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum StoredToken {
+    Live,
+    Expired,
+    /// Already rotated or revoked; presenting it again means it leaked.
+    Spent,
+}
+
+/// Reuse is checked before expiry so a stolen token still burns its family after it expires.
+fn stored_token_state(
+    used_at: Option<NaiveDateTime>,
+    revoked_at: Option<NaiveDateTime>,
+    expires_at: NaiveDateTime,
+    now: NaiveDateTime,
+) -> StoredToken {
+    if used_at.is_some() || revoked_at.is_some() {
+        StoredToken::Spent
+    } else if expires_at <= now {
+        StoredToken::Expired
+    } else {
+        StoredToken::Live
+    }
+}
+
+#[cfg(test)]
+mod tests_refresh_token_repo {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn used_or_revoked_tokens_count_as_reuse_even_when_expired() {
+        let now = Utc::now().naive_utc();
+        let later = now + Duration::days(1);
+        let earlier = now - Duration::days(1);
+
+        assert_eq!(
+            stored_token_state(None, None, later, now),
+            StoredToken::Live
+        );
+        assert_eq!(
+            stored_token_state(None, None, earlier, now),
+            StoredToken::Expired
+        );
+        assert_eq!(
+            stored_token_state(Some(earlier), None, later, now),
+            StoredToken::Spent
+        );
+        assert_eq!(
+            stored_token_state(None, Some(earlier), earlier, now),
+            StoredToken::Spent
+        );
+    }
+}
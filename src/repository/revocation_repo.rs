@@ -0,0 +1,81 @@
+use actix_web::web::Data;
+use chrono::NaiveDateTime;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+
+use crate::utils::errors::AppError;
+
+/// Expiries are naive UTC and compared against a time bound from Rust, since SQL `now()` would
+/// follow the session time zone.
+pub struct RevocationRepo;
+
+impl RevocationRepo {
+    pub async fn revoke_token(
+        jti: &str,
+        expires_at: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"INSERT INTO revoked_tokens (jti, expires_at) VALUES ($1, $2) ON CONFLICT (jti) DO NOTHING"#,
+        )
+        .bind(jti)
+        .bind(expires_at)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    /// Returns every revocation that still matters, i.e. whose token has not expired yet.
+    pub async fn fetch_active(
+        now: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<Vec<(String, i64)>, AppError> {
+        let rows: Vec<PgRow> =
+            sqlx::query(r#"SELECT jti, expires_at FROM revoked_tokens WHERE expires_at > $1"#)
+                .bind(now)
+                .fetch_all(pool.as_ref())
+                .await?;
+        let mut revoked = Vec::new();
+        for row in rows {
+            let expires_at: NaiveDateTime = row.get("expires_at");
+            revoked.push((row.get("jti"), expires_at.and_utc().timestamp()));
+        }
+        Ok(revoked)
+    }
+
+    pub async fn purge_expired(now: NaiveDateTime, pool: &Data<PgPool>) -> Result<u64, AppError> {
+        let result = sqlx::query(r#"DELETE FROM revoked_tokens WHERE expires_at <= $1"#)
+            .bind(now)
+            .execute(pool.as_ref())
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn set_cutoff(
+        user_id: i32,
+        not_before: NaiveDateTime,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"INSERT INTO token_cutoffs (user_id, not_before) VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET not_before = GREATEST(token_cutoffs.not_before, EXCLUDED.not_before)"#,
+        )
+        .bind(user_id)
+        .bind(not_before)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn fetch_cutoffs(pool: &Data<PgPool>) -> Result<Vec<(i32, i64)>, AppError> {
+        let rows: Vec<PgRow> = sqlx::query(r#"SELECT user_id, not_before FROM token_cutoffs"#)
+            .fetch_all(pool.as_ref())
+            .await?;
+        let mut cutoffs = Vec::new();
+        for row in rows {
+            let not_before: NaiveDateTime = row.get("not_before");
+            cutoffs.push((row.get("user_id"), not_before.and_utc().timestamp()));
+        }
+        Ok(cutoffs)
+    }
+}
@@ -1,17 +1,21 @@
 use actix_web::web::Data;
+use chrono::NaiveDateTime;
 use sqlx::postgres::PgRow;
 use sqlx::{PgPool, Row};
-use std::error::Error;
 
-use crate::utils::types::{RegisterUser, UserDetails, UserLogin, Users};
+use crate::utils::errors::{is_unique_violation, AppError};
+use crate::utils::types::{
+    RegisterUser, UpdateProfile, UserDetails, UserLogin, UserProfile, Users,
+};
 
 pub struct UserRepo;
 
 impl UserRepo {
-    pub async fn fetch_users_list(pool: &Data<PgPool>) -> Result<Vec<Users>, Box<dyn Error>> {
-        let rows: Vec<PgRow> = sqlx::query("SELECT user_login FROM app_users")
-            .fetch_all(pool.as_ref())
-            .await?;
+    pub async fn fetch_users_list(pool: &Data<PgPool>) -> Result<Vec<Users>, AppError> {
+        let rows: Vec<PgRow> =
+            sqlx::query("SELECT user_login FROM app_users WHERE is_active = TRUE")
+                .fetch_all(pool.as_ref())
+                .await?;
         let mut users_list = vec![];
         for row in rows {
             users_list.push(Users {
@@ -21,45 +25,214 @@ impl UserRepo {
         Ok(users_list)
     }
 
+    /// Inserts the user together with their first email verification token, in one
+    /// transaction, and returns the new id; a taken `user_login` or email is a 409.
     pub async fn user_registration(
         payload: RegisterUser,
         pool: &Data<PgPool>,
         sec_hash: String,
-    ) -> Result<i32, Box<dyn Error>> {
-        let result = sqlx::query(
-            r#"INSERT INTO app_users (user_name, sec, user_login, address) VALUES ($1,$2,$3,$4)"#,
+        verification_hash: &str,
+        verification_expires_at: NaiveDateTime,
+    ) -> Result<i32, AppError> {
+        let mut tx = pool.begin().await?;
+        let user_id: i32 = sqlx::query_scalar(
+            r#"INSERT INTO app_users (user_name, sec, user_login, address, email) VALUES ($1,$2,$3,$4,$5) RETURNING id"#,
         )
         .bind(&payload.user_name)
         .bind(sec_hash)
         .bind(&payload.user_login)
         .bind(&payload.address)
-        .execute(pool.as_ref())
+        .bind(&payload.email)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(registration_error)?;
+        sqlx::query(
+            r#"INSERT INTO email_verification_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3)"#,
+        )
+        .bind(user_id)
+        .bind(verification_hash)
+        .bind(verification_expires_at)
+        .execute(&mut *tx)
         .await?;
-
-        if result.rows_affected() > 0 {
-            let user_row: i32 =
-                sqlx::query_scalar(r#"SELECT id from app_users WHERE user_login = $1"#)
-                    .bind(&payload.user_login)
-                    .fetch_one(pool.as_ref())
-                    .await?;
-
-            return Ok(user_row);
-        }
-        Err("Record not inserted!!".into())
+        tx.commit().await?;
+        Ok(user_id)
     }
 
     pub async fn fetch_one_user(
         payload: &UserLogin,
         pool: &Data<PgPool>,
-    ) -> Result<UserDetails, Box<dyn Error>> {
-        let row = sqlx::query("SELECT id, sec from app_users WHERE user_login = $1")
-            .bind(&payload.user_login)
-            .fetch_one(pool.as_ref())
-            .await?;
-        let user_details = UserDetails {
+    ) -> Result<Option<UserDetails>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"SELECT id, sec, roles, email_verified_at, totp_enabled_at IS NOT NULL AS totp_enabled
+            FROM app_users WHERE user_login = $1 AND is_active = TRUE"#,
+        )
+        .bind(&payload.user_login)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        let user_details = row.map(|row| UserDetails {
             id: row.get("id"),
             sec: row.get("sec"),
-        };
+            roles: row.get("roles"),
+            email_verified_at: row.get("email_verified_at"),
+            totp_enabled: row.get("totp_enabled"),
+        });
         Ok(user_details)
     }
+
+    /// Login and password hash of the account, for checks of the current password.
+    pub async fn fetch_credentials(
+        user_id: i32,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<(String, String)>, AppError> {
+        let row: Option<PgRow> = sqlx::query("SELECT user_login, sec FROM app_users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(pool.as_ref())
+            .await?;
+        Ok(row.map(|row| (row.get("user_login"), row.get("sec"))))
+    }
+
+    /// Id and mail address of the account; accounts without an email get mail at their login.
+    pub async fn fetch_contact(
+        user_login: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<(i32, String)>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"SELECT id, COALESCE(email, user_login) AS email FROM app_users
+            WHERE user_login = $1 AND is_active = TRUE"#,
+        )
+        .bind(user_login)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(row.map(|row| (row.get("id"), row.get("email"))))
+    }
+
+    pub async fn update_password_hash(
+        user_id: i32,
+        sec_hash: &str,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        sqlx::query("UPDATE app_users SET sec = $1 WHERE id = $2")
+            .bind(sec_hash)
+            .bind(user_id)
+            .execute(pool.as_ref())
+            .await?;
+        Ok(())
+    }
+
+    /// Stored roles of the user and whether TOTP is enabled, which admin sessions depend on.
+    pub async fn fetch_roles(
+        user_id: i32,
+        pool: &Data<PgPool>,
+    ) -> Result<(Vec<String>, bool), AppError> {
+        let row: PgRow = sqlx::query(
+            r#"SELECT roles, totp_enabled_at IS NOT NULL AS totp_enabled FROM app_users WHERE id = $1"#,
+        )
+        .bind(user_id)
+        .fetch_one(pool.as_ref())
+        .await?;
+        Ok((row.get("roles"), row.get("totp_enabled")))
+    }
+
+    pub async fn fetch_profile(
+        user_id: i32,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<UserProfile>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"SELECT id, user_name, user_login, address, email, created_at, updated_at
+            FROM app_users WHERE id = $1 AND is_active = TRUE"#,
+        )
+        .bind(user_id)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(row.map(profile_from_row))
+    }
+
+    /// Applies the fields present in `changes` and returns the new profile, or `None` if the
+    /// user does not exist or is deactivated.
+    pub async fn update_profile(
+        user_id: i32,
+        changes: &UpdateProfile,
+        pool: &Data<PgPool>,
+    ) -> Result<Option<UserProfile>, AppError> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"UPDATE app_users SET
+                user_name = COALESCE($1, user_name),
+                address = COALESCE($2, address),
+                updated_at = now()
+            WHERE id = $3 AND is_active = TRUE
+            RETURNING id, user_name, user_login, address, email, created_at, updated_at"#,
+        )
+        .bind(&changes.user_name)
+        .bind(&changes.address)
+        .bind(user_id)
+        .fetch_optional(pool.as_ref())
+        .await?;
+        Ok(row.map(profile_from_row))
+    }
+
+    pub async fn deactivate_user(user_id: i32, pool: &Data<PgPool>) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"UPDATE app_users SET is_active = $1, updated_at = now() WHERE id = $2 AND is_active = $3"#,
+        )
+        .bind(false)
+        .bind(user_id)
+        .bind(true)
+        .execute(pool.as_ref())
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+fn profile_from_row(row: PgRow) -> UserProfile {
+    UserProfile {
+        id: row.get("id"),
+        user_name: row.get("user_name"),
+        user_login: row.get("user_login"),
+        address: row.get("address"),
+        email: row.get("email"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// A taken `user_login` or email is a 409 naming which one; anything else keeps the generic
+/// mapping.
+fn registration_error(e: sqlx::Error) -> AppError {
+    match &e {
+        sqlx::Error::Database(db_err) if is_unique_violation(&e) => {
+            if db_err.constraint() == Some("uq_app_users_email") {
+                AppError::Conflict(String::from("Email already registered !!"))
+            } else {
+                AppError::Conflict(String::from("Login already taken !!"))
+            }
+        }
+        _ => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests_user_repo {
+    use super::*;
+    use crate::utils::test_support::unique_violation;
+
+    #[test]
+    fn duplicate_registrations_are_conflicts_naming_the_field() {
+        let AppError::Conflict(msg) = registration_error(unique_violation("uq_app_users_email"))
+        else {
+            panic!("email conflict expected");
+        };
+        assert_eq!(msg, "Email already registered !!");
+
+        let AppError::Conflict(msg) =
+            registration_error(unique_violation("uq_app_users_user_login"))
+        else {
+            panic!("login conflict expected");
+        };
+        assert_eq!(msg, "Login already taken !!");
+
+        assert!(matches!(
+            registration_error(sqlx::Error::PoolTimedOut),
+            AppError::Internal(_)
+        ));
+    }
 }
@@ -1,30 +1,590 @@
-use actix_web::web::{self, get, post, resource, scope, ServiceConfig};
+use actix_web::middleware::from_fn;
+use actix_web::web::{self, delete, get, patch, post, resource, scope, ServiceConfig};
 
-use crate::controllers::health::{check_health, not_found};
+use crate::controllers::health::{check_health, hashing_stats, not_found};
+use crate::controllers::mfa::{confirm_totp, setup_totp, verify_mfa};
+use crate::controllers::oidc::{oidc_callback, oidc_start};
 use crate::controllers::orders::{add_order, get_one_order, get_order_list, remove_order};
+use crate::controllers::password::{change_password, forgot_password, reset_password};
+use crate::controllers::profile::{
+    deactivate_user, get_my_profile, get_user_profile, update_my_profile, update_user_profile,
+};
+use crate::controllers::session::{jwks, logout, refresh_session};
 use crate::controllers::status::{check_user, save_user_test};
+use crate::controllers::tokens::{
+    create_api_key, create_personal_token, list_api_keys, revoke_api_key,
+};
 use crate::controllers::user::{fetch_all, register_user, user_login};
+use crate::controllers::verification::{resend_verification, verify_email};
+use crate::middlewares::auth::{require_auth, require_role, require_scope, require_session};
+use crate::utils::constants::{ADMIN_ROLE, ORDERS_READ, ORDERS_WRITE};
 
 pub fn init(cfg: &mut ServiceConfig) {
     cfg.service(
         scope("api/v1")
             .service(resource("/check_status").route(get().to(check_health)))
+            .service(
+                resource("/metrics/password_hashing")
+                    .wrap(from_fn(require_role(ADMIN_ROLE)))
+                    .route(get().to(hashing_stats)),
+            )
             .service(
                 scope("/users")
                     .service(resource("/login").route(post().to(user_login)))
                     .service(resource("/register").route(post().to(register_user)))
-                    .service(resource("/fetch_all").route(get().to(fetch_all))),
+                    .service(resource("/refresh").route(post().to(refresh_session)))
+                    .service(resource("/logout").route(post().to(logout)))
+                    .service(
+                        resource("/change_password")
+                            .wrap(from_fn(require_session))
+                            .route(post().to(change_password)),
+                    )
+                    .service(resource("/forgot_password").route(post().to(forgot_password)))
+                    .service(resource("/reset_password").route(post().to(reset_password)))
+                    .service(resource("/verify").route(get().to(verify_email)))
+                    .service(resource("/resend_verification").route(post().to(resend_verification)))
+                    .service(resource("/mfa/verify").route(post().to(verify_mfa)))
+                    .service(resource("/oidc/start").route(get().to(oidc_start)))
+                    .service(resource("/oidc/callback").route(get().to(oidc_callback)))
+                    .service(
+                        resource("/mfa/totp/setup")
+                            .wrap(from_fn(require_session))
+                            .route(post().to(setup_totp)),
+                    )
+                    .service(
+                        resource("/mfa/totp/confirm")
+                            .wrap(from_fn(require_session))
+                            .route(post().to(confirm_totp)),
+                    )
+                    .service(
+                        resource("/tokens")
+                            .wrap(from_fn(require_session))
+                            .route(post().to(create_personal_token)),
+                    )
+                    .service(
+                        resource("/api_keys")
+                            .wrap(from_fn(require_session))
+                            .route(post().to(create_api_key))
+                            .route(get().to(list_api_keys)),
+                    )
+                    .service(
+                        resource("/api_keys/{key_id}")
+                            .wrap(from_fn(require_session))
+                            .route(delete().to(revoke_api_key)),
+                    )
+                    .service(
+                        resource("/fetch_all")
+                            .wrap(from_fn(require_role(ADMIN_ROLE)))
+                            .route(get().to(fetch_all)),
+                    )
+                    .service(
+                        resource("/me")
+                            .wrap(from_fn(require_session))
+                            .route(get().to(get_my_profile))
+                            .route(patch().to(update_my_profile)),
+                    )
+                    .service(
+                        resource("/{user_id:\\d+}")
+                            .wrap(from_fn(require_role(ADMIN_ROLE)))
+                            .route(get().to(get_user_profile))
+                            .route(patch().to(update_user_profile))
+                            .route(delete().to(deactivate_user)),
+                    ),
             )
             .service(
                 scope("/orders")
-                    .service(resource("create_order").route(post().to(add_order)))
-                    .service(resource("/delete_order").route(get().to(remove_order)))
-                    .service(resource("/get_one").route(get().to(get_one_order)))
-                    .service(resource("order_list").route(get().to(get_order_list))),
+                    .wrap(from_fn(require_auth))
+                    .service(
+                        resource("create_order")
+                            .wrap(from_fn(require_scope(ORDERS_WRITE)))
+                            .route(post().to(add_order)),
+                    )
+                    .service(
+                        resource("/delete_order")
+                            .wrap(from_fn(require_scope(ORDERS_WRITE)))
+                            .route(delete().to(remove_order)),
+                    )
+                    .service(
+                        resource("/get_one")
+                            .wrap(from_fn(require_scope(ORDERS_READ)))
+                            .route(get().to(get_one_order)),
+                    )
+                    .service(
+                        resource("order_list")
+                            .wrap(from_fn(require_scope(ORDERS_READ)))
+                            .route(get().to(get_order_list)),
+                    ),
             )
             .service(resource("/check_user_status").route(get().to(check_user)))
             .service(resource("/save_user_test").route(post().to(save_user_test))),
     );
+    cfg.service(resource("/.well-known/jwks.json").route(get().to(jwks)));
     //catch all routes
     cfg.default_service(web::to(not_found));
 }
+
+#[cfg(test)]
+mod tests_routes {
+    use super::*;
+    use actix_web::body::MessageBody;
+    use actix_web::cookie::Cookie;
+    use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::Next;
+    use actix_web::web::Data;
+    use actix_web::{test, App, Error, HttpMessage};
+
+    use crate::controllers::session::session_roles;
+    use crate::middlewares::auth::authenticate_request;
+    use crate::utils::config::AppConfig;
+    use crate::utils::constants::{
+        ALL_SCOPES, COOKIE_NAME, CSRF_COOKIE_NAME, CSRF_HEADER, REFRESH_COOKIE_NAME,
+    };
+    use crate::utils::jwt_impl::{generate_jwt_token, generate_mfa_token, generate_personal_token};
+    use crate::utils::revocation::RevocationStore;
+    use crate::utils::test_support::{lazy_pool, test_hashing_pool, test_keys, test_mailer};
+    use crate::utils::types::AuthenticatedUser;
+    use crate::{reaches_database, status_of};
+
+    /// The full router behind `authenticate_request`, with `test_keys` and a database that is
+    /// never reached.
+    fn test_app() -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody>,
+            Error = Error,
+            InitError = (),
+        >,
+    > {
+        App::new()
+            .app_data(Data::new(lazy_pool()))
+            .app_data(Data::new(AppConfig::default()))
+            .app_data(Data::new(RevocationStore::default()))
+            .app_data(Data::new(test_keys()))
+            .app_data(Data::new(test_hashing_pool()))
+            .app_data(test_mailer())
+            .wrap(from_fn(authenticate_request))
+            .configure(init)
+    }
+
+    /// Stands in for `authenticate_request` accepting an API key that holds every scope, since
+    /// the key lookup itself needs the database.
+    async fn as_api_key(
+        req: ServiceRequest,
+        next: Next<impl MessageBody>,
+    ) -> Result<ServiceResponse<impl MessageBody>, Error> {
+        req.extensions_mut().insert(AuthenticatedUser {
+            user_id: 7,
+            roles: Vec::new(),
+            scopes: ALL_SCOPES.iter().map(|s| s.to_string()).collect(),
+            token_id: None,
+            expires_at: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp(),
+            session: false,
+        });
+        next.call(req).await
+    }
+
+    fn session_token(user_id: i32, roles: &[String]) -> String {
+        generate_jwt_token(user_id, roles, &AppConfig::default(), &test_keys()).unwrap()
+    }
+
+    fn personal_token(user_id: i32, scopes: &[&str]) -> String {
+        let scopes: Vec<String> = scopes.iter().map(|s| s.to_string()).collect();
+        generate_personal_token(
+            user_id,
+            &scopes,
+            chrono::Duration::days(1),
+            &AppConfig::default(),
+            &test_keys(),
+        )
+        .unwrap()
+    }
+
+    fn bearer(token: &str) -> (&'static str, String) {
+        ("Authorization", format!("Bearer {}", token))
+    }
+
+    #[actix_web::test]
+    async fn protected_routes_reject_anonymous_requests() {
+        let app = test::init_service(test_app()).await;
+
+        for uri in [
+            "/api/v1/orders/order_list",
+            "/api/v1/orders/get_one?order_id=1",
+            "/api/v1/users/fetch_all",
+            "/api/v1/users/api_keys",
+            "/api/v1/users/me",
+            "/api/v1/users/7",
+        ] {
+            let req = test::TestRequest::get().uri(uri);
+            assert_eq!(status_of!(&app, req), StatusCode::UNAUTHORIZED, "{}", uri);
+        }
+
+        let login = test::TestRequest::post()
+            .uri("/api/v1/users/login")
+            .set_json(serde_json::json!({"user_login": "a", "sec": "b"}));
+        assert!(reaches_database!(&app, login));
+
+        let malformed_key = test::TestRequest::get()
+            .uri("/api/v1/orders/order_list")
+            .insert_header(("X-Api-Key", "not-a-key"));
+        assert_eq!(status_of!(&app, malformed_key), StatusCode::UNAUTHORIZED);
+
+        let squat = test::TestRequest::post()
+            .uri("/api/v1/users/register")
+            .set_json(serde_json::json!({
+                "user_name": "Mallory",
+                "sec": "correct horse 9",
+                "user_login": "oidc_0123456789abcdef0123",
+                "address": "",
+                "email": "mallory@example.com"
+            }));
+        assert_eq!(status_of!(&app, squat), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Public: reaches validation without any credential.
+        let resend = test::TestRequest::post()
+            .uri("/api/v1/users/resend_verification")
+            .set_json(serde_json::json!({"user_login": ""}));
+        assert_eq!(status_of!(&app, resend), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // The mail limit is checked before anything is looked up or sent.
+        let resend = test::TestRequest::post()
+            .uri("/api/v1/users/resend_verification")
+            .set_json(serde_json::json!({"user_login": "jane"}));
+        assert!(reaches_database!(&app, resend));
+
+        let verify_without_token = test::TestRequest::get().uri("/api/v1/users/verify");
+        assert_eq!(
+            status_of!(&app, verify_without_token),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn personal_tokens_are_limited_to_their_scopes() {
+        let app = test::init_service(test_app()).await;
+        let session = session_token(7, &[]);
+        let read_only = personal_token(7, &[ORDERS_READ]);
+
+        let create = test::TestRequest::post()
+            .uri("/api/v1/orders/create_order")
+            .insert_header(bearer(&read_only))
+            .set_json(serde_json::json!({"description": "x"}));
+        assert_eq!(status_of!(&app, create), StatusCode::FORBIDDEN);
+
+        let delete = test::TestRequest::delete()
+            .uri("/api/v1/orders/delete_order?order_id=1")
+            .insert_header(bearer(&read_only));
+        assert_eq!(status_of!(&app, delete), StatusCode::FORBIDDEN);
+
+        let list = test::TestRequest::get()
+            .uri("/api/v1/orders/order_list")
+            .insert_header(bearer(&read_only));
+        assert!(reaches_database!(&app, list));
+
+        let one = test::TestRequest::get()
+            .uri("/api/v1/orders/get_one?order_id=999")
+            .insert_header(bearer(&read_only));
+        assert!(reaches_database!(&app, one));
+
+        let bad_id = test::TestRequest::get()
+            .uri("/api/v1/orders/get_one?order_id=abc")
+            .insert_header(bearer(&read_only));
+        assert_eq!(status_of!(&app, bad_id), StatusCode::BAD_REQUEST);
+
+        let widen = test::TestRequest::post()
+            .uri("/api/v1/users/tokens")
+            .insert_header(bearer(&read_only))
+            .set_json(serde_json::json!({"scopes": [ORDERS_WRITE]}));
+        assert_eq!(status_of!(&app, widen), StatusCode::FORBIDDEN);
+
+        let same_scope = test::TestRequest::post()
+            .uri("/api/v1/users/tokens")
+            .insert_header(bearer(&read_only))
+            .set_json(serde_json::json!({"scopes": [ORDERS_READ]}));
+        assert_eq!(status_of!(&app, same_scope), StatusCode::FORBIDDEN);
+
+        let too_many_scopes = test::TestRequest::post()
+            .uri("/api/v1/users/tokens")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({"scopes": vec![ORDERS_READ; 11]}));
+        assert_eq!(
+            status_of!(&app, too_many_scopes),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let mint = test::TestRequest::post()
+            .uri("/api/v1/users/tokens")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({"scopes": [ORDERS_READ], "expires_in_days": 7}));
+        assert_eq!(status_of!(&app, mint), StatusCode::OK);
+
+        let other_profile = test::TestRequest::delete()
+            .uri("/api/v1/users/8")
+            .insert_header(bearer(&session));
+        assert_eq!(status_of!(&app, other_profile), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn api_keys_cannot_manage_the_account() {
+        let app = test::init_service(test_app().wrap(from_fn(as_api_key))).await;
+
+        let orders = test::TestRequest::get().uri("/api/v1/orders/order_list");
+        assert!(reaches_database!(&app, orders));
+
+        let requests = [
+            test::TestRequest::get().uri("/api/v1/users/api_keys"),
+            test::TestRequest::post()
+                .uri("/api/v1/users/api_keys")
+                .set_json(serde_json::json!({"name": "ci", "scopes": [ORDERS_READ]})),
+            test::TestRequest::delete().uri("/api/v1/users/api_keys/1"),
+            test::TestRequest::post()
+                .uri("/api/v1/users/tokens")
+                .set_json(serde_json::json!({"scopes": [ORDERS_READ]})),
+            test::TestRequest::get().uri("/api/v1/users/me"),
+            test::TestRequest::post()
+                .uri("/api/v1/users/change_password")
+                .set_json(serde_json::json!({"old_sec": "a", "new_sec": "correct horse 9"})),
+            test::TestRequest::patch()
+                .uri("/api/v1/users/me")
+                .set_json(serde_json::json!({"user_name": "x"})),
+        ];
+        for req in requests {
+            assert_eq!(status_of!(&app, req), StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[actix_web::test]
+    async fn api_key_requests_are_validated() {
+        let app = test::init_service(test_app()).await;
+        let session = session_token(7, &[]);
+
+        for body in [
+            serde_json::json!({"name": " ", "scopes": [ORDERS_READ]}),
+            serde_json::json!({"name": "x".repeat(101), "scopes": [ORDERS_READ]}),
+            serde_json::json!({"name": "ci", "scopes": vec![ORDERS_READ; 11]}),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/v1/users/api_keys")
+                .insert_header(bearer(&session))
+                .set_json(&body);
+            assert_eq!(
+                status_of!(&app, req),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "{}",
+                body
+            );
+        }
+
+        let valid = test::TestRequest::post()
+            .uri("/api/v1/users/api_keys")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({"name": "ci", "scopes": [ORDERS_READ]}));
+        assert!(reaches_database!(&app, valid));
+    }
+
+    #[actix_web::test]
+    async fn profile_routes_check_input_before_the_database() {
+        let app = test::init_service(test_app()).await;
+        let session = session_token(7, &[]);
+        let admin = session_token(1, &session_roles(vec![String::from(ADMIN_ROLE)], true));
+
+        let blank_name = test::TestRequest::patch()
+            .uri("/api/v1/users/me")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({"user_name": " "}));
+        assert_eq!(
+            status_of!(&app, blank_name),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let rename = test::TestRequest::patch()
+            .uri("/api/v1/users/me")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({"user_name": "Jane"}));
+        assert!(reaches_database!(&app, rename));
+
+        let long_address = test::TestRequest::patch()
+            .uri("/api/v1/users/7")
+            .insert_header(bearer(&admin))
+            .set_json(serde_json::json!({"address": "x".repeat(501)}));
+        assert_eq!(
+            status_of!(&app, long_address),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let deactivate_self = test::TestRequest::delete()
+            .uri("/api/v1/users/1")
+            .insert_header(bearer(&admin));
+        assert_eq!(status_of!(&app, deactivate_self), StatusCode::BAD_REQUEST);
+
+        let deactivate_other = test::TestRequest::delete()
+            .uri("/api/v1/users/7")
+            .insert_header(bearer(&admin));
+        assert!(reaches_database!(&app, deactivate_other));
+    }
+
+    #[actix_web::test]
+    async fn password_routes_check_input_before_the_database() {
+        let app = test::init_service(test_app()).await;
+        let session = session_token(7, &[]);
+
+        let weak = test::TestRequest::post()
+            .uri("/api/v1/users/change_password")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({"old_sec": "old", "new_sec": "12345678"}));
+        assert_eq!(status_of!(&app, weak), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // The throttle is consulted before the current password is checked.
+        let change = test::TestRequest::post()
+            .uri("/api/v1/users/change_password")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({"old_sec": "old", "new_sec": "correct horse 9"}));
+        assert!(reaches_database!(&app, change));
+
+        let forgot_blank = test::TestRequest::post()
+            .uri("/api/v1/users/forgot_password")
+            .set_json(serde_json::json!({"user_login": ""}));
+        assert_eq!(
+            status_of!(&app, forgot_blank),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let forgot = test::TestRequest::post()
+            .uri("/api/v1/users/forgot_password")
+            .set_json(serde_json::json!({"user_login": "jane"}));
+        assert!(reaches_database!(&app, forgot));
+
+        let reset_weak = test::TestRequest::post()
+            .uri("/api/v1/users/reset_password")
+            .set_json(serde_json::json!({"token": "t", "new_sec": "short"}));
+        assert_eq!(
+            status_of!(&app, reset_weak),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let reset = test::TestRequest::post()
+            .uri("/api/v1/users/reset_password")
+            .set_json(serde_json::json!({"token": "t", "new_sec": "correct horse 9"}));
+        assert!(reaches_database!(&app, reset));
+    }
+
+    #[actix_web::test]
+    async fn mfa_tokens_are_not_access_tokens() {
+        let app = test::init_service(test_app()).await;
+        let mfa_token = generate_mfa_token(7, &AppConfig::default(), &test_keys()).unwrap();
+        let session = session_token(7, &[]);
+
+        let setup = test::TestRequest::post()
+            .uri("/api/v1/users/mfa/totp/setup")
+            .insert_header(bearer(&mfa_token));
+        assert_eq!(status_of!(&app, setup), StatusCode::UNAUTHORIZED);
+
+        let full_scope_token = personal_token(7, &[ORDERS_READ, ORDERS_WRITE]);
+        for uri in [
+            "/api/v1/users/mfa/totp/setup",
+            "/api/v1/users/mfa/totp/confirm",
+        ] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .insert_header(bearer(&full_scope_token))
+                .set_json(serde_json::json!({"sec": "hunter2", "code": "123456"}));
+            assert_eq!(status_of!(&app, req), StatusCode::FORBIDDEN, "{}", uri);
+        }
+
+        let without_password = test::TestRequest::post()
+            .uri("/api/v1/users/mfa/totp/setup")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({}));
+        assert_eq!(status_of!(&app, without_password), StatusCode::BAD_REQUEST);
+
+        // The throttle is consulted before the password is checked.
+        let with_password = test::TestRequest::post()
+            .uri("/api/v1/users/mfa/totp/setup")
+            .insert_header(bearer(&session))
+            .set_json(serde_json::json!({"sec": "hunter2"}));
+        assert!(reaches_database!(&app, with_password));
+
+        let swapped = test::TestRequest::post()
+            .uri("/api/v1/users/mfa/verify")
+            .set_json(serde_json::json!({"mfa_token": session, "code": "123456"}));
+        assert_eq!(status_of!(&app, swapped), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn csrf_is_only_enforced_where_cookies_are_used() {
+        let app = test::init_service(test_app()).await;
+        let session = format!("Bearer {}", session_token(7, &[]));
+
+        let login = test::TestRequest::post()
+            .uri("/api/v1/users/login")
+            .cookie(Cookie::new(COOKIE_NAME, session.clone()))
+            .set_json(serde_json::json!({"user_login": "a", "sec": "b"}));
+        assert!(reaches_database!(&app, login));
+
+        let update = test::TestRequest::patch()
+            .uri("/api/v1/users/me")
+            .cookie(Cookie::new(COOKIE_NAME, session.clone()))
+            .set_json(serde_json::json!({"user_name": "x"}));
+        assert_eq!(status_of!(&app, update), StatusCode::FORBIDDEN);
+
+        // A cross-site <img> can send the cookie on a GET, which skips the CSRF check.
+        let get_delete = test::TestRequest::get()
+            .uri("/api/v1/orders/delete_order?order_id=1")
+            .cookie(Cookie::new(COOKIE_NAME, session.clone()));
+        assert_eq!(status_of!(&app, get_delete), StatusCode::METHOD_NOT_ALLOWED);
+
+        let delete_without_csrf = test::TestRequest::delete()
+            .uri("/api/v1/orders/delete_order?order_id=1")
+            .cookie(Cookie::new(COOKIE_NAME, session.clone()));
+        assert_eq!(status_of!(&app, delete_without_csrf), StatusCode::FORBIDDEN);
+
+        let logout_without_csrf = test::TestRequest::post()
+            .uri("/api/v1/users/logout")
+            .cookie(Cookie::new(COOKIE_NAME, session.clone()));
+        assert_eq!(status_of!(&app, logout_without_csrf), StatusCode::FORBIDDEN);
+
+        let logout_with_csrf = test::TestRequest::post()
+            .uri("/api/v1/users/logout")
+            .cookie(Cookie::new(COOKIE_NAME, session.clone()))
+            .cookie(Cookie::new(CSRF_COOKIE_NAME, "abc"))
+            .insert_header((CSRF_HEADER, "abc"));
+        assert!(reaches_database!(&app, logout_with_csrf));
+
+        let logout_anonymous = test::TestRequest::post().uri("/api/v1/users/logout");
+        assert_eq!(status_of!(&app, logout_anonymous), StatusCode::OK);
+
+        for uri in ["/api/v1/users/refresh", "/api/v1/users/logout"] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .cookie(Cookie::new(REFRESH_COOKIE_NAME, "refresh"));
+            assert_eq!(status_of!(&app, req), StatusCode::FORBIDDEN, "{}", uri);
+        }
+
+        let with_csrf = test::TestRequest::post()
+            .uri("/api/v1/users/refresh")
+            .cookie(Cookie::new(REFRESH_COOKIE_NAME, "refresh"))
+            .cookie(Cookie::new(CSRF_COOKIE_NAME, "abc"))
+            .insert_header((CSRF_HEADER, "abc"));
+        assert!(reaches_database!(&app, with_csrf));
+    }
+
+    #[actix_web::test]
+    async fn admins_without_totp_get_no_admin_session() {
+        let app = test::init_service(test_app()).await;
+        let admin = vec![String::from(ADMIN_ROLE)];
+        let without_totp = session_token(1, &session_roles(admin.clone(), false));
+        let with_totp = session_token(1, &session_roles(admin, true));
+
+        let denied = test::TestRequest::get()
+            .uri("/api/v1/users/fetch_all")
+            .insert_header(bearer(&without_totp));
+        assert_eq!(status_of!(&app, denied), StatusCode::FORBIDDEN);
+
+        let allowed = test::TestRequest::get()
+            .uri("/api/v1/users/fetch_all")
+            .insert_header(bearer(&with_totp));
+        assert!(reaches_database!(&app, allowed));
+    }
+}
@@ -0,0 +1,186 @@
+use std::env;
+use std::time;
+
+use chrono::Duration;
+use dotenv::dotenv;
+
+use super::helpers::env_flag;
+use super::password::PasswordAlgorithm;
+
+/// Settings read once at startup and shared with handlers as `Data<AppConfig>`.
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub access_token_ttl: Duration,
+    pub refresh_token_ttl: Duration,
+    pub revocation_sync_interval: time::Duration,
+    /// Written to the `iss` claim and required on every incoming token.
+    pub jwt_issuer: String,
+    /// Written to the `aud` claim and required on every incoming token.
+    pub jwt_audience: String,
+    /// Clock skew tolerated when checking `exp`, in seconds.
+    pub jwt_leeway: u64,
+    /// Failed logins for one account before it is locked.
+    pub login_max_failures: i32,
+    /// Failed logins from one client IP, across accounts, before it is throttled.
+    pub login_ip_max_failures: i32,
+    /// First lockout; each further failure doubles it up to `login_lockout_max`.
+    pub login_lockout_base: Duration,
+    pub login_lockout_max: Duration,
+    /// Algorithm for new password hashes; see `PasswordHasher`.
+    pub password_algorithm: PasswordAlgorithm,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub bcrypt_cost: u32,
+    /// Password hashes computed at once; further requests wait in `HashingPool`.
+    pub password_hash_concurrency: usize,
+    pub password_reset_ttl: Duration,
+    /// Link mailed for password resets; the token is appended as `?token=...`.
+    pub password_reset_url: String,
+    /// Reset and verification mails per submitted login within `mail_window`.
+    pub mail_max_per_login: i32,
+    /// Reset and verification mails per client IP, across logins, within `mail_window`.
+    pub mail_max_per_ip: i32,
+    pub mail_window: Duration,
+    /// When set, `user_login` refuses accounts whose email is not verified yet.
+    pub require_email_verification: bool,
+    pub email_verification_ttl: Duration,
+    /// Link mailed after registration; the token is appended as `?token=...`.
+    pub email_verification_url: String,
+    /// Lifetime of the token that bridges a correct password and the second factor.
+    pub mfa_token_ttl: Duration,
+    /// Issuer shown by authenticator apps next to the account.
+    pub totp_issuer: String,
+    /// Issuer URL of the OpenID Connect provider; SSO login is off when unset.
+    pub oidc_issuer: Option<String>,
+    pub oidc_client_id: String,
+    /// Sent with the code exchange; public clients rely on PKCE alone.
+    pub oidc_client_secret: Option<String>,
+    /// Must match the redirect URI registered at the provider.
+    pub oidc_redirect_url: String,
+    pub oidc_scopes: String,
+}
+
+impl AppConfig {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        AppConfig {
+            access_token_ttl: Duration::minutes(env_number("ACCESS_TOKEN_TTL_MINUTES", 15)),
+            refresh_token_ttl: Duration::days(env_number("REFRESH_TOKEN_TTL_DAYS", 14)),
+            revocation_sync_interval: time::Duration::from_secs(env_number(
+                "REVOCATION_SYNC_SECONDS",
+                60,
+            ) as u64),
+            jwt_issuer: env_string("JWT_ISSUER", "vipin"),
+            jwt_audience: env_string("JWT_AUDIENCE", "actix-crud"),
+            jwt_leeway: env_number("JWT_LEEWAY_SECONDS", 30) as u64,
+            login_max_failures: env_number("LOGIN_MAX_FAILURES", 5) as i32,
+            login_ip_max_failures: env_number("LOGIN_IP_MAX_FAILURES", 50) as i32,
+            login_lockout_base: Duration::seconds(env_number("LOGIN_LOCKOUT_SECONDS", 30)),
+            login_lockout_max: Duration::seconds(env_number("LOGIN_LOCKOUT_MAX_SECONDS", 3600)),
+            password_algorithm: env_string("PASSWORD_ALGORITHM", "argon2id")
+                .parse()
+                .unwrap_or_else(|e| panic!("PASSWORD_ALGORITHM :: {}", e)),
+            argon2_memory_kib: env_number("ARGON2_MEMORY_KIB", 19 * 1024) as u32,
+            argon2_iterations: env_number("ARGON2_ITERATIONS", 2) as u32,
+            argon2_parallelism: env_number("ARGON2_PARALLELISM", 1) as u32,
+            bcrypt_cost: env_number("BCRYPT_COST", bcrypt::DEFAULT_COST as i64) as u32,
+            password_hash_concurrency: env_number(
+                "PASSWORD_HASH_CONCURRENCY",
+                default_hash_concurrency() as i64,
+            ) as usize,
+            password_reset_ttl: Duration::minutes(env_number("PASSWORD_RESET_TTL_MINUTES", 30)),
+            password_reset_url: env_string(
+                "PASSWORD_RESET_URL",
+                "http://127.0.0.1:5173/reset-password",
+            ),
+            mail_max_per_login: env_number("MAIL_MAX_PER_LOGIN", 3) as i32,
+            mail_max_per_ip: env_number("MAIL_MAX_PER_IP", 20) as i32,
+            mail_window: Duration::minutes(env_number("MAIL_WINDOW_MINUTES", 60)),
+            require_email_verification: env_flag("REQUIRE_EMAIL_VERIFICATION"),
+            email_verification_ttl: Duration::hours(env_number("EMAIL_VERIFICATION_TTL_HOURS", 48)),
+            email_verification_url: env_string(
+                "EMAIL_VERIFICATION_URL",
+                "http://127.0.0.1:6002/api/v1/users/verify",
+            ),
+            mfa_token_ttl: Duration::seconds(env_number("MFA_TOKEN_TTL_SECONDS", 300)),
+            totp_issuer: env_string("TOTP_ISSUER", "actix-crud"),
+            oidc_issuer: env_optional("OIDC_ISSUER"),
+            oidc_client_id: env_string("OIDC_CLIENT_ID", "actix-crud"),
+            oidc_client_secret: env_optional("OIDC_CLIENT_SECRET"),
+            oidc_redirect_url: env_string(
+                "OIDC_REDIRECT_URL",
+                "http://127.0.0.1:6002/api/v1/users/oidc/callback",
+            ),
+            oidc_scopes: env_string("OIDC_SCOPES", "openid email profile"),
+        }
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            access_token_ttl: Duration::minutes(15),
+            refresh_token_ttl: Duration::days(14),
+            revocation_sync_interval: time::Duration::from_secs(60),
+            jwt_issuer: String::from("vipin"),
+            jwt_audience: String::from("actix-crud"),
+            jwt_leeway: 30,
+            login_max_failures: 5,
+            login_ip_max_failures: 50,
+            login_lockout_base: Duration::seconds(30),
+            login_lockout_max: Duration::seconds(3600),
+            password_algorithm: PasswordAlgorithm::Argon2id,
+            argon2_memory_kib: 19 * 1024,
+            argon2_iterations: 2,
+            argon2_parallelism: 1,
+            bcrypt_cost: bcrypt::DEFAULT_COST,
+            password_hash_concurrency: default_hash_concurrency(),
+            password_reset_ttl: Duration::minutes(30),
+            password_reset_url: String::from("http://127.0.0.1:5173/reset-password"),
+            mail_max_per_login: 3,
+            mail_max_per_ip: 20,
+            mail_window: Duration::minutes(60),
+            require_email_verification: false,
+            email_verification_ttl: Duration::hours(48),
+            email_verification_url: String::from("http://127.0.0.1:6002/api/v1/users/verify"),
+            mfa_token_ttl: Duration::seconds(300),
+            totp_issuer: String::from("actix-crud"),
+            oidc_issuer: None,
+            oidc_client_id: String::from("actix-crud"),
+            oidc_client_secret: None,
+            oidc_redirect_url: String::from("http://127.0.0.1:6002/api/v1/users/oidc/callback"),
+            oidc_scopes: String::from("openid email profile"),
+        }
+    }
+}
+
+fn env_number(key: &str, default: i64) -> i64 {
+    match env::var(key) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a number", key)),
+        Err(_) => default,
+    }
+}
+
+/// One hash per CPU: hashing is CPU bound, so more would only queue inside the OS instead.
+fn default_hash_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(2)
+}
+
+fn env_string(key: &str, default: &str) -> String {
+    env::var(key)
+        .map(|value| value.trim().to_string())
+        .unwrap_or_else(|_| default.to_string())
+}
+
+fn env_optional(key: &str) -> Option<String> {
+    env::var(key)
+        .map(|value| value.trim().to_string())
+        .ok()
+        .filter(|value| !value.is_empty())
+}
@@ -13,6 +13,30 @@ pub const HEADERS: &[header::HeaderName] =
 
 pub const COOKIE_NAME: &str = "OKIJ";
 
+/// Double-submit cookie paired with `CSRF_HEADER` whenever `COOKIE_NAME` authenticates a request.
+pub const CSRF_COOKIE_NAME: &str = "OKIJ_CSRF";
+
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+pub const REFRESH_COOKIE_NAME: &str = "OKIJ_REFRESH";
+
+/// Holds the signed state of an SSO login between `oidc_start` and `oidc_callback`.
+pub const OIDC_COOKIE_NAME: &str = "OKIJ_OIDC";
+
+/// Start of the logins derived for SSO users; `RegisterUser` refuses it so nobody can claim an
+/// SSO user's login before their first sign-in.
+pub const OIDC_LOGIN_PREFIX: &str = "oidc_";
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+pub const ADMIN_ROLE: &str = "admin";
+
+pub const ORDERS_READ: &str = "orders:read";
+pub const ORDERS_WRITE: &str = "orders:write";
+
+/// Every scope a token can carry. Session tokens get all of them; personal access tokens a subset.
+pub const ALL_SCOPES: &[&str] = &[ORDERS_READ, ORDERS_WRITE];
+
 #[cfg(test)]
 mod tests_constants {
     use super::*;
@@ -22,24 +46,35 @@ mod tests_constants {
     #[test]
     fn methods_list_matches_expected_and_ordered() {
         // Exact content and ordering
-        assert_eq\!(
+        assert_eq!(
             METHODS,
-            &[Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::PATCH]
+            &[
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::DELETE,
+                Method::PATCH
+            ]
         );
         // Length sanity check
-        assert_eq\!(METHODS.len(), 5);
+        assert_eq!(METHODS.len(), 5);
     }
 
     #[test]
     fn methods_are_unique_and_exclude_disallowed() {
         // Uniqueness by semantic identity (method string)
         let unique: HashSet<_> = METHODS.iter().map(|m| m.as_str()).collect();
-        assert_eq\!(unique.len(), METHODS.len(), "METHODS contains duplicates");
+        assert_eq!(unique.len(), METHODS.len(), "METHODS contains duplicates");
 
         // Ensure unexpected/unsafe methods are not permitted
-        for disallowed in [Method::HEAD, Method::OPTIONS, Method::CONNECT, Method::TRACE] {
-            assert\!(
-                \!METHODS.contains(&disallowed),
+        for disallowed in [
+            Method::HEAD,
+            Method::OPTIONS,
+            Method::CONNECT,
+            Method::TRACE,
+        ] {
+            assert!(
+                !METHODS.contains(&disallowed),
                 "Unexpected disallowed method present: {}",
                 disallowed
             );
@@ -49,27 +84,30 @@ mod tests_constants {
     #[test]
     fn headers_match_expected_and_unique() {
         // Exact content and ordering
-        assert_eq\!(
+        assert_eq!(
             HEADERS,
             &[header::AUTHORIZATION, header::ACCEPT, header::CONTENT_TYPE]
         );
         // Uniqueness via canonical string form
         let unique: HashSet<_> = HEADERS.iter().map(|h| h.as_str()).collect();
-        assert_eq\!(unique.len(), HEADERS.len(), "HEADERS contains duplicates");
+        assert_eq!(unique.len(), HEADERS.len(), "HEADERS contains duplicates");
         // Length sanity check
-        assert_eq\!(HEADERS.len(), 3);
+        assert_eq!(HEADERS.len(), 3);
     }
 
     #[test]
     fn cookie_name_is_expected_and_well_formed() {
-        assert_eq\!(COOKIE_NAME, "OKIJ");
-        assert\!(\!COOKIE_NAME.is_empty(), "COOKIE_NAME should not be empty");
-        assert\!(COOKIE_NAME.is_ascii(), "COOKIE_NAME should be ASCII");
-        assert\!(
-            \!COOKIE_NAME.chars().any(|c| c.is_whitespace()),
+        assert_eq!(COOKIE_NAME, "OKIJ");
+        assert!(!COOKIE_NAME.is_empty(), "COOKIE_NAME should not be empty");
+        assert!(COOKIE_NAME.is_ascii(), "COOKIE_NAME should be ASCII");
+        assert!(
+            !COOKIE_NAME.chars().any(|c| c.is_whitespace()),
             "COOKIE_NAME should not contain whitespace"
         );
         // Optional: keep cookie token short and simple
-        assert\!(COOKIE_NAME.len() <= 16, "COOKIE_NAME should be reasonably short");
+        assert!(
+            COOKIE_NAME.len() <= 16,
+            "COOKIE_NAME should be reasonably short"
+        );
     }
 }
@@ -0,0 +1,258 @@
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpResponse, ResponseError};
+use bcrypt::BcryptError;
+use jsonwebtoken::errors::ErrorKind;
+use log::error;
+use thiserror::Error;
+
+use super::validation::FieldError;
+use crate::controllers::api_responses::ApiResponse;
+
+/// Crate-wide error type returned by repositories and handlers.
+///
+/// Every variant maps to an HTTP status and is rendered as an `ApiResponse` body, so handlers
+/// can simply use `?`. Internal failures are logged but never echoed back to the client.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Validation(String),
+    /// Well-formed payload that breaks validation rules; rendered as 422 with every field.
+    #[error("Validation failed !!")]
+    Unprocessable(Vec<FieldError>),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    InvalidToken(TokenError),
+    #[error("{0}")]
+    Forbidden(String),
+    /// Too many attempts from this client; retry after the given number of seconds.
+    #[error("{0}")]
+    TooManyRequests(String, i64),
+    /// The account is temporarily locked; retry after the given number of seconds.
+    #[error("{0}")]
+    Locked(String, i64),
+    #[error("Internal error :: {0}")]
+    Internal(String),
+}
+
+/// Why a bearer token was refused. Rendered as a 401 whose `results` carries `code()`, so
+/// clients can tell an expired token (refresh it) from one that will never be accepted.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum TokenError {
+    #[error("Token expired !!")]
+    Expired,
+    #[error("Token not valid yet !!")]
+    NotYetValid,
+    #[error("Invalid token audience !!")]
+    InvalidAudience,
+    #[error("Invalid token issuer !!")]
+    InvalidIssuer,
+    #[error("Invalid token signature !!")]
+    InvalidSignature,
+    #[error("Token revoked !!")]
+    Revoked,
+    #[error("Invalid token !!")]
+    Malformed,
+}
+
+impl TokenError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenError::Expired => "token_expired",
+            TokenError::NotYetValid => "token_not_yet_valid",
+            TokenError::InvalidAudience => "invalid_audience",
+            TokenError::InvalidIssuer => "invalid_issuer",
+            TokenError::InvalidSignature => "invalid_signature",
+            TokenError::Revoked => "token_revoked",
+            TokenError::Malformed => "invalid_token",
+        }
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for TokenError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        match e.kind() {
+            ErrorKind::ExpiredSignature => TokenError::Expired,
+            ErrorKind::ImmatureSignature => TokenError::NotYetValid,
+            ErrorKind::InvalidAudience => TokenError::InvalidAudience,
+            ErrorKind::InvalidIssuer => TokenError::InvalidIssuer,
+            ErrorKind::InvalidSignature | ErrorKind::InvalidAlgorithm => {
+                TokenError::InvalidSignature
+            }
+            _ => TokenError::Malformed,
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unauthorized(_) | AppError::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::TooManyRequests(..) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Locked(..) => StatusCode::LOCKED,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let msg = match self {
+            AppError::Internal(e) => {
+                error!("Internal error occurred!! {}", e);
+                String::from("Internal server error !!")
+            }
+            other => other.to_string(),
+        };
+        if let AppError::Unprocessable(fields) = self {
+            return HttpResponse::build(status).json(ApiResponse {
+                status: status.as_u16() as i32,
+                msg,
+                results: Some(fields.clone()),
+            });
+        }
+        if let AppError::TooManyRequests(_, retry_after) | AppError::Locked(_, retry_after) = self {
+            return HttpResponse::build(status)
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(ApiResponse::<String> {
+                    status: status.as_u16() as i32,
+                    msg,
+                    results: None,
+                });
+        }
+        if let AppError::InvalidToken(reason) = self {
+            return HttpResponse::build(status)
+                .insert_header((
+                    header::WWW_AUTHENTICATE,
+                    format!(
+                        "Bearer error=\"invalid_token\", error_description=\"{}\"",
+                        reason.code()
+                    ),
+                ))
+                .json(ApiResponse::<String> {
+                    status: status.as_u16() as i32,
+                    msg,
+                    results: Some(reason.code().to_string()),
+                });
+        }
+        HttpResponse::build(status).json(ApiResponse::<String> {
+            status: status.as_u16() as i32,
+            msg,
+            results: None,
+        })
+    }
+}
+
+/// Postgres SQLSTATE for `unique_violation`.
+pub const UNIQUE_VIOLATION: &str = "23505";
+
+pub fn is_unique_violation(e: &sqlx::Error) -> bool {
+    matches!(e, sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some(UNIQUE_VIOLATION))
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => AppError::NotFound(String::from("Record not found !!")),
+            ref e if is_unique_violation(e) => {
+                AppError::Conflict(String::from("Record already exists !!"))
+            }
+            e => AppError::Internal(format!("{:?}", e)),
+        }
+    }
+}
+
+impl From<BcryptError> for AppError {
+    fn from(e: BcryptError) -> Self {
+        AppError::Internal(format!("{:?}", e))
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for AppError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        AppError::Internal(format!("{:?}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests_errors {
+    use super::*;
+    use actix_web::body::to_bytes;
+
+    #[test]
+    fn variants_map_to_expected_status() {
+        let cases = [
+            (AppError::NotFound("x".into()), StatusCode::NOT_FOUND),
+            (AppError::Conflict("x".into()), StatusCode::CONFLICT),
+            (AppError::Validation("x".into()), StatusCode::BAD_REQUEST),
+            (
+                AppError::Unprocessable(Vec::new()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (AppError::Unauthorized("x".into()), StatusCode::UNAUTHORIZED),
+            (
+                AppError::InvalidToken(TokenError::Expired),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (AppError::Forbidden("x".into()), StatusCode::FORBIDDEN),
+            (
+                AppError::TooManyRequests("x".into(), 1),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (AppError::Locked("x".into(), 1), StatusCode::LOCKED),
+            (
+                AppError::Internal("x".into()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(err.status_code(), expected, "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn row_not_found_becomes_not_found() {
+        let err: AppError = sqlx::Error::RowNotFound.into();
+        assert!(matches!(err, AppError::NotFound(_)));
+    }
+
+    #[test]
+    fn unique_violations_become_conflicts() {
+        let err: AppError = crate::utils::test_support::unique_violation("uq_anything").into();
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn internal_error_body_does_not_leak_details() {
+        let err = AppError::Internal("relation \"orders\" does not exist".into());
+        let body = to_bytes(err.error_response().into_body()).await.unwrap();
+        let body: ApiResponse<String> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.status, 500);
+        assert!(!body.msg.contains("orders"));
+        assert!(body.results.is_none());
+    }
+
+    #[actix_web::test]
+    async fn invalid_token_body_carries_reason_code() {
+        let resp = AppError::InvalidToken(TokenError::InvalidAudience).error_response();
+        assert!(resp
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("invalid_audience"));
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let body: ApiResponse<String> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.status, 401);
+        assert_eq!(body.results.as_deref(), Some("invalid_audience"));
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use actix_web::rt::task::spawn_blocking;
+use log::debug;
+use tokio::sync::Semaphore;
+
+use super::errors::AppError;
+use super::password::PasswordHasher;
+use super::types::PasswordHashingStats;
+
+/// Runs `PasswordHasher` work off the actix workers, shared as `Data<HashingPool>`.
+///
+/// A single Argon2 or bcrypt call takes tens to hundreds of milliseconds of CPU; doing it
+/// inline would stall every other request on the same worker. Jobs run on the blocking pool,
+/// at most `max_concurrency` at a time, so a login burst queues here instead of starving the
+/// rest of the server. Time spent waiting for a slot is recorded for `stats()`.
+pub struct HashingPool {
+    hasher: Arc<PasswordHasher>,
+    permits: Arc<Semaphore>,
+    waiting: AtomicUsize,
+    started: AtomicU64,
+    total_wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+}
+
+impl HashingPool {
+    pub fn new(hasher: PasswordHasher, max_concurrency: usize) -> Self {
+        HashingPool {
+            hasher: Arc::new(hasher),
+            permits: Arc::new(Semaphore::new(max_concurrency.max(1))),
+            waiting: AtomicUsize::new(0),
+            started: AtomicU64::new(0),
+            total_wait_micros: AtomicU64::new(0),
+            max_wait_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn hash(&self, password: String) -> Result<String, AppError> {
+        self.run(move |hasher| hasher.hash(&password)).await
+    }
+
+    pub async fn verify_login(
+        &self,
+        stored: Option<String>,
+        password: String,
+    ) -> Result<bool, AppError> {
+        self.run(move |hasher| hasher.verify_login(stored.as_deref(), &password))
+            .await
+    }
+
+    /// Only parses the stored string, so it is cheap enough to call inline.
+    pub fn needs_rehash(&self, stored: &str) -> bool {
+        self.hasher.needs_rehash(stored)
+    }
+
+    pub fn stats(&self) -> PasswordHashingStats {
+        let started = self.started.load(Ordering::Relaxed);
+        let total_wait = self.total_wait_micros.load(Ordering::Relaxed);
+        PasswordHashingStats {
+            available_slots: self.permits.available_permits(),
+            waiting: self.waiting.load(Ordering::Relaxed),
+            started,
+            avg_wait_ms: if started == 0 {
+                0.0
+            } else {
+                total_wait as f64 / started as f64 / 1000.0
+            },
+            max_wait_ms: self.max_wait_micros.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+
+    async fn run<T, F>(&self, job: F) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&PasswordHasher) -> Result<T, AppError> + Send + 'static,
+    {
+        let queued_at = Instant::now();
+        let queued = Waiting::enter(&self.waiting);
+        let permit = self.permits.clone().acquire_owned().await;
+        drop(queued);
+        let permit = permit.map_err(|_| AppError::Internal(String::from("Hashing pool closed")))?;
+
+        let waited = queued_at.elapsed().as_micros() as u64;
+        self.started.fetch_add(1, Ordering::Relaxed);
+        self.total_wait_micros.fetch_add(waited, Ordering::Relaxed);
+        self.max_wait_micros.fetch_max(waited, Ordering::Relaxed);
+        debug!("Password hashing slot acquired after {}us", waited);
+
+        let hasher = self.hasher.clone();
+        spawn_blocking(move || {
+            let _permit = permit;
+            job(&hasher)
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Hashing task failed :: {}", e)))?
+    }
+}
+
+/// Counts a job as waiting until dropped, so a request cancelled while queued (e.g. the client
+/// disconnected) does not leave the counter raised.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn enter(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Waiting(counter)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests_hashing_pool {
+    use super::*;
+    use crate::utils::config::AppConfig;
+
+    #[actix_web::test]
+    async fn jobs_queue_behind_the_concurrency_limit() {
+        let hasher = PasswordHasher::new(&AppConfig {
+            argon2_memory_kib: 1024,
+            argon2_iterations: 1,
+            ..AppConfig::default()
+        })
+        .unwrap();
+        let pool = HashingPool::new(hasher, 1);
+
+        let (first, second) = tokio::join!(
+            pool.hash(String::from("hunter2a")),
+            pool.hash(String::from("hunter2b")),
+        );
+        let stored = first.unwrap();
+        second.unwrap();
+        assert!(pool
+            .verify_login(Some(stored), String::from("hunter2a"))
+            .await
+            .unwrap());
+
+        let stats = pool.stats();
+        assert_eq!(stats.started, 3);
+        assert_eq!(stats.waiting, 0);
+        assert_eq!(stats.available_slots, 1);
+        assert!(stats.max_wait_ms > 0.0);
+    }
+
+    #[actix_web::test]
+    async fn cancelled_jobs_stop_counting_as_waiting() {
+        let hasher = PasswordHasher::new(&AppConfig {
+            argon2_memory_kib: 1024,
+            argon2_iterations: 1,
+            ..AppConfig::default()
+        })
+        .unwrap();
+        let pool = HashingPool::new(hasher, 1);
+        let held = pool.permits.clone().acquire_owned().await.unwrap();
+
+        let queued = pool.hash(String::from("hunter2a"));
+        let timed_out = tokio::time::timeout(std::time::Duration::from_millis(20), queued).await;
+        assert!(timed_out.is_err());
+        drop(held);
+
+        let stats = pool.stats();
+        assert_eq!(stats.waiting, 0);
+        assert_eq!(stats.started, 0);
+    }
+
+    #[actix_web::test]
+    async fn unknown_logins_cost_one_verification_like_wrong_passwords() {
+        let pool = crate::utils::test_support::test_hashing_pool();
+        let stored = pool.hash(String::from("hunter2a")).await.unwrap();
+
+        let before = pool.stats().started;
+        assert!(!pool
+            .verify_login(None, String::from("hunter2a"))
+            .await
+            .unwrap());
+        let unknown = pool.stats().started - before;
+        assert!(!pool
+            .verify_login(Some(stored), String::from("wrong"))
+            .await
+            .unwrap());
+        let wrong = pool.stats().started - before - unknown;
+        assert_eq!((unknown, wrong), (1, 1));
+    }
+}
@@ -1,10 +1,15 @@
 use std::env;
 
 use actix_web::cookie::time::Duration;
-use actix_web::cookie::Cookie;
+use actix_web::cookie::{Cookie, SameSite};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use dotenv::dotenv;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
 
-use super::constants::COOKIE_NAME;
+use super::constants::{COOKIE_NAME, CSRF_COOKIE_NAME, OIDC_COOKIE_NAME, REFRESH_COOKIE_NAME};
 
 pub fn get_conn_url() -> String {
     dotenv().ok();
@@ -25,11 +30,86 @@ pub fn get_conn_url() -> String {
     )
 }
 
-pub fn build_auth_cookie(token: String) -> Cookie<'static> {
+/// Reads a boolean switch from the environment; `1`, `true` and `yes` enable it.
+pub fn env_flag(key: &str) -> bool {
+    dotenv().ok();
+    env::var(key)
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+pub fn build_auth_cookie(token: String, max_age: chrono::Duration) -> Cookie<'static> {
     Cookie::build(COOKIE_NAME, token)
         .http_only(true)
-        // .same_site(SameSite::Strict)  // Commented out as in original register_user
+        .same_site(SameSite::None)
+        .secure(true)
+        .path("/")
+        .max_age(Duration::seconds(max_age.num_seconds()))
+        .finish()
+}
+
+/// Readable by the browser on purpose: clients echo it back in `CSRF_HEADER`.
+pub fn build_csrf_cookie(token: String, max_age: chrono::Duration) -> Cookie<'static> {
+    Cookie::build(CSRF_COOKIE_NAME, token)
+        .same_site(SameSite::None)
+        .secure(true)
         .path("/")
-        .max_age(Duration::hours(2))
+        .max_age(Duration::seconds(max_age.num_seconds()))
         .finish()
 }
+
+/// Only sent back to the session endpoints under `/api/v1/users`.
+pub fn build_refresh_cookie(token: String, max_age: chrono::Duration) -> Cookie<'static> {
+    Cookie::build(REFRESH_COOKIE_NAME, token)
+        .http_only(true)
+        .same_site(SameSite::None)
+        .secure(true)
+        .path("/api/v1/users")
+        .max_age(Duration::seconds(max_age.num_seconds()))
+        .finish()
+}
+
+/// Lax rather than None: the provider sends the browser back with a top-level GET.
+pub fn build_oidc_cookie(token: String, max_age: chrono::Duration) -> Cookie<'static> {
+    Cookie::build(OIDC_COOKIE_NAME, token)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(true)
+        .path("/api/v1/users/oidc")
+        .max_age(Duration::seconds(max_age.num_seconds()))
+        .finish()
+}
+
+/// URL-safe random string built from `bytes` bytes of OS randomness.
+pub fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::rng().fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+/// Hex-encoded SHA-256, used to store high-entropy secrets such as refresh tokens.
+pub fn sha256_hex(value: &str) -> String {
+    format!("{:x}", Sha256::digest(value.as_bytes()))
+}
+
+/// Generates an API key as `(prefix, secret, full key)`; the full key is `ak_<prefix>.<secret>`.
+pub fn generate_api_key() -> (String, String, String) {
+    let prefix = random_token(6);
+    let secret = random_token(32);
+    let key = format!("ak_{}.{}", prefix, secret);
+    (prefix, secret, key)
+}
+
+/// Splits a presented API key into `(prefix, secret)`.
+pub fn parse_api_key(key: &str) -> Option<(&str, &str)> {
+    let (prefix, secret) = key.strip_prefix("ak_")?.split_once('.')?;
+    if prefix.is_empty() || secret.is_empty() {
+        None
+    } else {
+        Some((prefix, secret))
+    }
+}
@@ -1,45 +1,142 @@
-use std::env;
-
-use super::types::Claims;
-use bcrypt::{hash, verify, BcryptError, DEFAULT_COST};
+use super::config::AppConfig;
+use super::constants::ALL_SCOPES;
+use super::keystore::KeyStore;
+use super::types::{Claims, MfaClaims, OidcLoginState};
 use chrono::{Duration, Utc};
-use dotenv::dotenv;
 use jsonwebtoken::errors::Error;
-use jsonwebtoken::{encode, EncodingKey, Header};
-use log::info;
+use jsonwebtoken::Validation;
+use uuid::Uuid;
+
+/// Session access token: carries the user's roles and every scope in `ALL_SCOPES`.
+pub fn generate_jwt_token(
+    user_id: i32,
+    roles: &[String],
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<String, Error> {
+    sign_token(
+        user_id,
+        roles,
+        &ALL_SCOPES.join(" "),
+        config.access_token_ttl,
+        true,
+        config,
+        keys,
+    )
+}
+
+/// Personal access token for automation: no roles, limited to `scopes`, lives for `ttl`.
+pub fn generate_personal_token(
+    user_id: i32,
+    scopes: &[String],
+    ttl: Duration,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<String, Error> {
+    sign_token(user_id, &[], &scopes.join(" "), ttl, false, config, keys)
+}
 
-pub fn generate_jwt_token(user_id: i32) -> Result<String, Error> {
-    dotenv().ok();
+fn sign_token(
+    user_id: i32,
+    roles: &[String],
+    scope: &str,
+    ttl: Duration,
+    session: bool,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<String, Error> {
     let now = Utc::now();
-    let exp = (now + Duration::days(2)).timestamp();
-    let encoding_key = env::var("ENCODING_KEY").expect("Key not found!!");
     let claims = Claims {
         sub: user_id,
-        exp,
-        // aud: String::from("Vipin"),
-        iss: String::from("vipin"),
-        iat: Utc::now().timestamp(),
+        exp: (now + ttl).timestamp(),
+        aud: config.jwt_audience.clone(),
+        iss: config.jwt_issuer.clone(),
+        iat: now.timestamp(),
+        jti: Uuid::new_v4().to_string(),
+        roles: roles.to_vec(),
+        scope: scope.to_string(),
+        session,
+    };
+
+    keys.encode(&claims)
+}
+
+/// Verifies an access token's signature, expiry, issuer and audience.
+pub fn decode_jwt_token(token: &str, config: &AppConfig, keys: &KeyStore) -> Result<Claims, Error> {
+    keys.decode::<Claims>(token, &token_validation(config))
+}
+
+/// Validation rules for access tokens; `KeyStore::decode` fills in the algorithm per key.
+pub fn token_validation(config: &AppConfig) -> Validation {
+    let mut validation = Validation::default();
+    validation.set_issuer(&[&config.jwt_issuer]);
+    validation.set_audience(&[&config.jwt_audience]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+    validation.leeway = config.jwt_leeway;
+    validation
+}
+
+/// Short-lived token proving the password was correct; exchanged at `verify_mfa` together
+/// with a second factor. It carries no roles or scopes and a separate audience, so it is never
+/// accepted as an access token.
+pub fn generate_mfa_token(
+    user_id: i32,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<String, Error> {
+    let now = Utc::now();
+    let claims = MfaClaims {
+        sub: user_id,
+        exp: (now + config.mfa_token_ttl).timestamp(),
+        aud: purpose_audience(config, "mfa"),
+        iss: config.jwt_issuer.clone(),
+        iat: now.timestamp(),
+        jti: Uuid::new_v4().to_string(),
     };
+    keys.encode(&claims)
+}
+
+pub fn decode_mfa_token(
+    token: &str,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<MfaClaims, Error> {
+    let mut validation = token_validation(config);
+    validation.set_audience(&[purpose_audience(config, "mfa")]);
+    keys.decode::<MfaClaims>(token, &validation)
+}
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(encoding_key.as_ref()),
-    );
-    token
-}
-
-pub fn get_hash(pass: &String) -> Result<String, BcryptError> {
-    let hash = match hash(pass, DEFAULT_COST) {
-        Ok(hash) => hash,
-        Err(e) => {
-            info!("Error occured!! {:?}", e);
-            return Err(e);
-        }
+/// Signs the SSO login state kept in `OIDC_COOKIE_NAME` for `ttl`.
+pub fn generate_oidc_state_token(
+    state: &str,
+    nonce: &str,
+    verifier: &str,
+    ttl: Duration,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<String, Error> {
+    let claims = OidcLoginState {
+        state: state.to_string(),
+        nonce: nonce.to_string(),
+        verifier: verifier.to_string(),
+        exp: (Utc::now() + ttl).timestamp(),
+        aud: purpose_audience(config, "oidc"),
+        iss: config.jwt_issuer.clone(),
     };
-    Ok(hash)
+    keys.encode(&claims)
+}
+
+pub fn decode_oidc_state_token(
+    token: &str,
+    config: &AppConfig,
+    keys: &KeyStore,
+) -> Result<OidcLoginState, Error> {
+    let mut validation = token_validation(config);
+    validation.set_audience(&[purpose_audience(config, "oidc")]);
+    keys.decode::<OidcLoginState>(token, &validation)
 }
 
-pub fn validate_hash(hash: String, sec: &String) -> Result<bool, BcryptError> {
-    verify(sec, &hash)
+/// Audience of internal tokens that must never pass as access tokens.
+fn purpose_audience(config: &AppConfig, purpose: &str) -> String {
+    format!("{}:{}", config.jwt_audience, purpose)
 }
@@ -0,0 +1,140 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use actix_web::rt::task::spawn_blocking;
+use actix_web::web::Data;
+use chrono::Utc;
+use dotenv::dotenv;
+use log::{error, info};
+
+use super::errors::AppError;
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Outgoing mail, shared as `Data<dyn Mailer>` so deployments can plug in a real transport.
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &Mail) -> Result<(), AppError>;
+}
+
+/// Writes mail to the application log. The default, meant for local development.
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, mail: &Mail) -> Result<(), AppError> {
+        info!("Mail to {} :: {}\n{}", mail.to, mail.subject, mail.body);
+        Ok(())
+    }
+}
+
+/// Appends mail to a file, e.g. for end-to-end tests that need to read reset links.
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileMailer { path: path.into() }
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: &Mail) -> Result<(), AppError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| AppError::Internal(format!("Cannot open {:?} :: {}", self.path, e)))?;
+        writeln!(
+            file,
+            "Date: {}\nTo: {}\nSubject: {}\n\n{}\n",
+            Utc::now().to_rfc2822(),
+            mail.to,
+            mail.subject,
+            mail.body
+        )
+        .map_err(|e| AppError::Internal(format!("Cannot write {:?} :: {}", self.path, e)))
+    }
+}
+
+/// Sends `mail` on the blocking pool without waiting for it.
+///
+/// `Mailer::send` may block on disk or network I/O, which must not happen on an actix worker.
+/// Not waiting also keeps response times independent of whether a mail was sent at all.
+/// Failures can only be logged.
+pub fn send_in_background(mailer: &Data<dyn Mailer>, mail: Mail) {
+    let mailer = mailer.clone();
+    spawn_blocking(move || {
+        if let Err(e) = mailer.send(&mail) {
+            error!("Failed to send mail '{}' :: {}", mail.subject, e);
+        }
+    });
+}
+
+/// `FileMailer` when `MAIL_FILE` is set, otherwise `LogMailer`.
+pub fn mailer_from_env() -> Arc<dyn Mailer> {
+    dotenv().ok();
+    match env::var("MAIL_FILE") {
+        Ok(path) => Arc::new(FileMailer::new(path)),
+        Err(_) => Arc::new(LogMailer),
+    }
+}
+
+#[cfg(test)]
+mod tests_mailer {
+    use super::*;
+
+    #[test]
+    fn file_mailer_appends_messages() {
+        let path = env::temp_dir().join(format!("actix-crud-mail-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mailer = FileMailer::new(&path);
+        for subject in ["first", "second"] {
+            mailer
+                .send(&Mail {
+                    to: String::from("alice@example.com"),
+                    subject: String::from(subject),
+                    body: String::from("hello"),
+                })
+                .unwrap();
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("Subject: first"));
+        assert!(contents.contains("Subject: second"));
+        assert_eq!(contents.matches("To: alice@example.com").count(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn background_sends_reach_the_mailer() {
+        let path = env::temp_dir().join(format!("actix-crud-mail-bg-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mailer: Data<dyn Mailer> =
+            Data::from(Arc::new(FileMailer::new(&path)) as Arc<dyn Mailer>);
+        send_in_background(
+            &mailer,
+            Mail {
+                to: String::from("alice@example.com"),
+                subject: String::from("background"),
+                body: String::from("hello"),
+            },
+        );
+        let mut contents = String::new();
+        for _ in 0..100 {
+            contents = std::fs::read_to_string(&path).unwrap_or_default();
+            if contents.contains("Subject: background") {
+                break;
+            }
+            actix_web::rt::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(contents.contains("Subject: background"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,17 @@
+pub mod config;
 pub mod constants;
+pub mod errors;
+pub mod hashing_pool;
 pub mod helpers;
 pub mod jwt_impl;
+pub mod keystore;
+pub mod mailer;
+pub mod oidc;
+pub mod password;
+pub mod revocation;
+#[cfg(test)]
+pub mod test_support;
+pub mod throttle;
+pub mod totp;
 pub mod types;
+pub mod validation;
@@ -0,0 +1,217 @@
+use std::str::FromStr;
+
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString};
+use argon2::{Argon2, Params};
+use bcrypt::HashParts;
+use rand::RngCore;
+
+use super::config::AppConfig;
+use super::errors::AppError;
+use super::helpers::random_token;
+
+/// Algorithm used for new hashes. Existing hashes are verified with whatever algorithm their
+/// stored string names, so switching the default never locks anybody out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordAlgorithm {
+    Bcrypt,
+    Argon2id,
+}
+
+impl FromStr for PasswordAlgorithm {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "bcrypt" => Ok(PasswordAlgorithm::Bcrypt),
+            "argon2id" | "argon2" => Ok(PasswordAlgorithm::Argon2id),
+            other => Err(format!("Unknown password algorithm {}", other)),
+        }
+    }
+}
+
+/// Hashes and verifies the passwords stored in `app_users.sec`, shared as `Data<PasswordHasher>`.
+///
+/// Stored hashes are self-describing: PHC strings (`$argon2id$...`) for Argon2 and the modular
+/// crypt format (`$2b$...`) for bcrypt. `needs_rehash` tells login to upgrade hashes that were
+/// made with another algorithm or weaker parameters.
+pub struct PasswordHasher {
+    algorithm: PasswordAlgorithm,
+    argon2_params: Params,
+    bcrypt_cost: u32,
+    dummy_hash: String,
+}
+
+impl PasswordHasher {
+    pub fn new(config: &AppConfig) -> Result<Self, AppError> {
+        let argon2_params = Params::new(
+            config.argon2_memory_kib,
+            config.argon2_iterations,
+            config.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| AppError::Internal(format!("Invalid Argon2 parameters :: {}", e)))?;
+        let mut hasher = PasswordHasher {
+            algorithm: config.password_algorithm,
+            argon2_params,
+            bcrypt_cost: config.bcrypt_cost,
+            dummy_hash: String::new(),
+        };
+        // A hash of a random password nobody knows, made with the current settings so that
+        // verifying against it costs the same as verifying a real, up-to-date hash.
+        hasher.dummy_hash = hasher.hash(&random_token(16))?;
+        Ok(hasher)
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String, AppError> {
+        match self.algorithm {
+            PasswordAlgorithm::Bcrypt => Ok(bcrypt::hash(password, self.bcrypt_cost)?),
+            PasswordAlgorithm::Argon2id => {
+                let mut salt = [0u8; 16];
+                rand::rng().fill_bytes(&mut salt);
+                let salt = SaltString::encode_b64(&salt).map_err(hash_error)?;
+                let hash = self
+                    .argon2()
+                    .hash_password(password.as_bytes(), &salt)
+                    .map_err(hash_error)?;
+                Ok(hash.to_string())
+            }
+        }
+    }
+
+    /// Verifies `password` against a stored hash of either supported algorithm.
+    pub fn verify(&self, stored: &str, password: &str) -> Result<bool, AppError> {
+        match detect(stored)? {
+            PasswordAlgorithm::Bcrypt => Ok(bcrypt::verify(password, stored)?),
+            PasswordAlgorithm::Argon2id => {
+                let parsed = PasswordHash::new(stored).map_err(hash_error)?;
+                match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+                    Ok(()) => Ok(true),
+                    Err(argon2::password_hash::Error::Password) => Ok(false),
+                    Err(e) => Err(hash_error(e)),
+                }
+            }
+        }
+    }
+
+    /// Like `verify`, but runs against the dummy hash when there is no stored hash, so unknown
+    /// logins cost the same verification as wrong passwords.
+    pub fn verify_login(&self, stored: Option<&str>, password: &str) -> Result<bool, AppError> {
+        match stored {
+            Some(stored) => self.verify(stored, password),
+            None => {
+                self.verify(&self.dummy_hash, password)?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Whether `stored` was made with a different algorithm or parameters than new hashes.
+    pub fn needs_rehash(&self, stored: &str) -> bool {
+        match (self.algorithm, detect(stored)) {
+            (PasswordAlgorithm::Bcrypt, Ok(PasswordAlgorithm::Bcrypt)) => {
+                HashParts::from_str(stored)
+                    .map(|parts| parts.get_cost() != self.bcrypt_cost)
+                    .unwrap_or(true)
+            }
+            (PasswordAlgorithm::Argon2id, Ok(PasswordAlgorithm::Argon2id)) => {
+                PasswordHash::new(stored)
+                    .ok()
+                    .filter(|parsed| parsed.algorithm == argon2::Algorithm::Argon2id.ident())
+                    .and_then(|parsed| Params::try_from(&parsed).ok())
+                    .map(|params| {
+                        params.m_cost() != self.argon2_params.m_cost()
+                            || params.t_cost() != self.argon2_params.t_cost()
+                            || params.p_cost() != self.argon2_params.p_cost()
+                    })
+                    .unwrap_or(true)
+            }
+            _ => true,
+        }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            self.argon2_params.clone(),
+        )
+    }
+}
+
+fn detect(stored: &str) -> Result<PasswordAlgorithm, AppError> {
+    if stored.starts_with("$argon2") {
+        Ok(PasswordAlgorithm::Argon2id)
+    } else if stored.starts_with("$2") {
+        Ok(PasswordAlgorithm::Bcrypt)
+    } else {
+        Err(AppError::Internal(String::from(
+            "Unknown password hash format",
+        )))
+    }
+}
+
+fn hash_error(e: argon2::password_hash::Error) -> AppError {
+    AppError::Internal(format!("{:?}", e))
+}
+
+#[cfg(test)]
+mod tests_password {
+    use super::*;
+
+    fn hasher(algorithm: PasswordAlgorithm, memory_kib: u32) -> PasswordHasher {
+        PasswordHasher::new(&AppConfig {
+            password_algorithm: algorithm,
+            argon2_memory_kib: memory_kib,
+            argon2_iterations: 1,
+            bcrypt_cost: 4,
+            ..AppConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn argon2id_round_trip() {
+        let hasher = hasher(PasswordAlgorithm::Argon2id, 1024);
+        let stored = hasher.hash("hunter2").unwrap();
+        assert!(stored.starts_with("$argon2id$"));
+        assert!(hasher.verify(&stored, "hunter2").unwrap());
+        assert!(!hasher.verify(&stored, "hunter3").unwrap());
+        assert!(!hasher.needs_rehash(&stored));
+    }
+
+    #[test]
+    fn legacy_bcrypt_verifies_and_needs_rehash() {
+        let legacy = hasher(PasswordAlgorithm::Bcrypt, 1024)
+            .hash("hunter2")
+            .unwrap();
+        let current = hasher(PasswordAlgorithm::Argon2id, 1024);
+        assert!(current.verify(&legacy, "hunter2").unwrap());
+        assert!(current.needs_rehash(&legacy));
+    }
+
+    #[test]
+    fn changed_argon2_params_need_rehash() {
+        let stored = hasher(PasswordAlgorithm::Argon2id, 1024)
+            .hash("hunter2")
+            .unwrap();
+        let stronger = hasher(PasswordAlgorithm::Argon2id, 2048);
+        assert!(stronger.verify(&stored, "hunter2").unwrap());
+        assert!(stronger.needs_rehash(&stored));
+    }
+
+    #[test]
+    fn unknown_user_never_verifies() {
+        let hasher = hasher(PasswordAlgorithm::Argon2id, 1024);
+        assert!(!hasher.verify_login(None, "hunter2").unwrap());
+    }
+
+    #[test]
+    fn dummy_hash_uses_current_settings_and_is_not_shared() {
+        for algorithm in [PasswordAlgorithm::Argon2id, PasswordAlgorithm::Bcrypt] {
+            let first = hasher(algorithm, 1024);
+            let second = hasher(algorithm, 1024);
+            assert!(!first.needs_rehash(&first.dummy_hash), "{:?}", algorithm);
+            assert_ne!(first.dummy_hash, second.dummy_hash);
+        }
+    }
+}
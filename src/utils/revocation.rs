@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use actix_web::web::Data;
+use chrono::{DateTime, Utc};
+use log::info;
+use sqlx::PgPool;
+
+use crate::repository::revocation_repo::RevocationRepo;
+
+use super::errors::AppError;
+
+/// In-memory view of the `revoked_tokens` and `token_cutoffs` tables.
+///
+/// `authenticate_request` consults only this cache so revocation checks never hit the
+/// database. Revocations made by this process are visible immediately; those made by other
+/// instances arrive with the next `sync`.
+#[derive(Debug, Default)]
+pub struct RevocationStore {
+    revoked: RwLock<HashMap<String, i64>>,
+    cutoffs: RwLock<HashMap<i32, i64>>,
+}
+
+impl RevocationStore {
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        self.revoked.read().unwrap().contains_key(jti)
+    }
+
+    /// Whether a token issued to `user_id` at `issued_at` predates the user's cutoff.
+    pub fn is_cut_off(&self, user_id: i32, issued_at: i64) -> bool {
+        self.cutoffs
+            .read()
+            .unwrap()
+            .get(&user_id)
+            .is_some_and(|not_before| issued_at < *not_before)
+    }
+
+    /// Rejects every access token already issued to `user_id`, personal tokens included.
+    pub async fn revoke_user(&self, user_id: i32, pool: &Data<PgPool>) -> Result<(), AppError> {
+        let now = Utc::now();
+        RevocationRepo::set_cutoff(user_id, now.naive_utc(), pool).await?;
+        self.cutoffs
+            .write()
+            .unwrap()
+            .insert(user_id, now.timestamp());
+        Ok(())
+    }
+
+    /// Revokes `jti` until `expires_at` (a unix timestamp), after which the token is dead anyway.
+    pub async fn revoke(
+        &self,
+        jti: &str,
+        expires_at: i64,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        let expiry = DateTime::<Utc>::from_timestamp(expires_at, 0)
+            .ok_or_else(|| AppError::Internal(format!("Invalid expiry :: {}", expires_at)))?;
+        RevocationRepo::revoke_token(jti, expiry.naive_utc(), pool).await?;
+        self.revoked
+            .write()
+            .unwrap()
+            .insert(jti.to_string(), expires_at);
+        Ok(())
+    }
+
+    /// Merges the tables into the cache and drops revocations that have expired on both sides.
+    pub async fn sync(&self, pool: &Data<PgPool>) -> Result<(), AppError> {
+        let now = Utc::now();
+        let purged = RevocationRepo::purge_expired(now.naive_utc(), pool).await?;
+        let active = RevocationRepo::fetch_active(now.naive_utc(), pool).await?;
+        let cutoffs = RevocationRepo::fetch_cutoffs(pool).await?;
+        info!(
+            "Revocation cache synced :: {} active, {} purged, {} cutoffs",
+            active.len(),
+            purged,
+            cutoffs.len()
+        );
+        self.merge(active, cutoffs, now.timestamp());
+        Ok(())
+    }
+
+    /// Applies rows read by `sync` as of `now`: revocations that have expired locally are
+    /// dropped and cutoffs only ever move forward.
+    pub(crate) fn merge(&self, active: Vec<(String, i64)>, cutoffs: Vec<(i32, i64)>, now: i64) {
+        let mut revoked = self.revoked.write().unwrap();
+        revoked.retain(|_, expires_at| *expires_at > now);
+        revoked.extend(active);
+
+        let mut current = self.cutoffs.write().unwrap();
+        for (user_id, not_before) in cutoffs {
+            let entry = current.entry(user_id).or_insert(not_before);
+            *entry = (*entry).max(not_before);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_revocation {
+    use super::*;
+
+    #[test]
+    fn cutoff_only_rejects_tokens_issued_before_it() {
+        let store = RevocationStore::default();
+        store.cutoffs.write().unwrap().insert(7, 1_000);
+        assert!(store.is_cut_off(7, 999));
+        assert!(!store.is_cut_off(7, 1_000));
+        assert!(!store.is_cut_off(8, 999));
+    }
+
+    #[test]
+    fn revoked_jtis_are_rejected_until_purged() {
+        let store = RevocationStore::default();
+        store.merge(
+            vec![(String::from("old"), 1_000), (String::from("live"), 3_000)],
+            Vec::new(),
+            0,
+        );
+        assert!(store.is_revoked("old"));
+        assert!(store.is_revoked("live"));
+        assert!(!store.is_revoked("other"));
+
+        store.merge(Vec::new(), Vec::new(), 2_000);
+        assert!(!store.is_revoked("old"));
+        assert!(store.is_revoked("live"));
+    }
+
+    #[test]
+    fn sync_never_moves_a_cutoff_back() {
+        let store = RevocationStore::default();
+        store.merge(Vec::new(), vec![(7, 2_000)], 0);
+        store.merge(Vec::new(), vec![(7, 1_000), (8, 500)], 0);
+        assert!(store.is_cut_off(7, 1_500));
+        assert!(store.is_cut_off(8, 499));
+    }
+}
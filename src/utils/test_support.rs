@@ -0,0 +1,123 @@
+//! Fixtures shared by the in-crate test modules.
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+use std::time::Duration;
+
+use actix_web::web::Data;
+use sqlx::error::{DatabaseError, ErrorKind};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+use super::config::AppConfig;
+use super::errors::UNIQUE_VIOLATION;
+use super::hashing_pool::HashingPool;
+use super::keystore::KeyStore;
+use super::mailer::{LogMailer, Mailer};
+use super::password::PasswordHasher;
+
+/// Status of the response, or of the error a middleware returned instead of one.
+#[macro_export]
+macro_rules! status_of {
+    ($app:expr, $req:expr) => {
+        match ::actix_web::test::try_call_service($app, $req.to_request()).await {
+            Ok(resp) => resp.status(),
+            Err(e) => e.as_response_error().status_code(),
+        }
+    };
+}
+
+/// Whether the request got past every guard and extractor and only failed on the database,
+/// which `lazy_pool` never reaches. Any other outcome, including an unrelated 500, is `false`.
+#[macro_export]
+macro_rules! reaches_database {
+    ($app:expr, $req:expr) => {
+        match ::actix_web::test::try_call_service($app, $req.to_request()).await {
+            Ok(resp) => resp
+                .response()
+                .error()
+                .is_some_and(|e| format!("{:?}", e).contains("PoolTimedOut")),
+            Err(_) => false,
+        }
+    };
+}
+
+/// Pool pointing at a closed port: every query fails with `PoolTimedOut`.
+pub fn lazy_pool() -> PgPool {
+    PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(100))
+        .connect_lazy("postgres://nobody@127.0.0.1:1/none")
+        .unwrap()
+}
+
+pub fn test_keys() -> KeyStore {
+    KeyStore::hmac("test", b"test-key")
+}
+
+/// Cheap Argon2 parameters, so tests that hash stay fast.
+pub fn test_hashing_pool() -> HashingPool {
+    let hasher = PasswordHasher::new(&AppConfig {
+        argon2_memory_kib: 1024,
+        argon2_iterations: 1,
+        ..AppConfig::default()
+    })
+    .unwrap();
+    HashingPool::new(hasher, 2)
+}
+
+pub fn test_mailer() -> Data<dyn Mailer> {
+    Data::from(std::sync::Arc::new(LogMailer) as std::sync::Arc<dyn Mailer>)
+}
+
+/// A unique violation on `constraint`, as Postgres reports it.
+pub fn unique_violation(constraint: &str) -> sqlx::Error {
+    sqlx::Error::Database(Box::new(FakeDatabaseError {
+        code: UNIQUE_VIOLATION,
+        constraint: Some(constraint.to_string()),
+    }))
+}
+
+#[derive(Debug)]
+struct FakeDatabaseError {
+    code: &'static str,
+    constraint: Option<String>,
+}
+
+impl fmt::Display for FakeDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error {} on {:?}", self.code, self.constraint)
+    }
+}
+
+impl StdError for FakeDatabaseError {}
+
+impl DatabaseError for FakeDatabaseError {
+    fn message(&self) -> &str {
+        "duplicate key value violates unique constraint"
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.code))
+    }
+
+    fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+        self
+    }
+
+    fn constraint(&self) -> Option<&str> {
+        self.constraint.as_deref()
+    }
+
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::UniqueViolation
+    }
+}
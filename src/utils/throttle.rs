@@ -0,0 +1,197 @@
+use actix_web::web::Data;
+use actix_web::HttpRequest;
+use chrono::{Duration, NaiveDateTime, Utc};
+use log::warn;
+use sqlx::PgPool;
+
+use super::config::AppConfig;
+use super::errors::AppError;
+use crate::repository::audit_repo::AuditRepo;
+use crate::repository::login_attempt_repo::LoginAttemptRepo;
+
+const LOGIN_SCOPE: &str = "login";
+const IP_SCOPE: &str = "ip";
+const MAIL_LOGIN_SCOPE: &str = "mail_login";
+const MAIL_IP_SCOPE: &str = "mail_ip";
+
+/// Failures older than this no longer count towards a lockout.
+const FAILURE_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/// Brute-force protection for `user_login`, tracking failures per submitted login and per
+/// client IP.
+///
+/// Logins are tracked whether or not the account exists, so a lockout says nothing about
+/// which accounts are real.
+pub struct LoginThrottle;
+
+impl LoginThrottle {
+    /// Rejects the attempt up front if the client IP (429) or the login (423) is locked.
+    pub async fn check(login: &str, client_ip: &str, pool: &Data<PgPool>) -> Result<(), AppError> {
+        let now = Utc::now().naive_utc();
+        if let Some(until) = LoginAttemptRepo::locked_until(IP_SCOPE, client_ip, now, pool).await? {
+            return Err(AppError::TooManyRequests(
+                String::from("Too many login attempts, try again later !!"),
+                retry_after(until, now),
+            ));
+        }
+        if let Some(until) = LoginAttemptRepo::locked_until(LOGIN_SCOPE, login, now, pool).await? {
+            return Err(AppError::Locked(
+                String::from("Account temporarily locked, try again later !!"),
+                retry_after(until, now),
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn record_failure(
+        login: &str,
+        client_ip: &str,
+        config: &AppConfig,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        let now = Utc::now().naive_utc();
+        for (scope, key, threshold) in [
+            (LOGIN_SCOPE, login, config.login_max_failures),
+            (IP_SCOPE, client_ip, config.login_ip_max_failures),
+        ] {
+            let failures =
+                LoginAttemptRepo::record_failure(scope, key, now, FAILURE_WINDOW_SECS, pool)
+                    .await?;
+            let Some(duration) = lockout_duration(
+                failures,
+                threshold,
+                config.login_lockout_base,
+                config.login_lockout_max,
+            ) else {
+                continue;
+            };
+            LoginAttemptRepo::lock(scope, key, now + duration, pool).await?;
+            warn!("Locked {} {} for {}s", scope, key, duration.num_seconds());
+            AuditRepo::record(
+                "login_lockout",
+                Some(login),
+                client_ip,
+                &format!(
+                    "scope={} failures={} seconds={}",
+                    scope,
+                    failures,
+                    duration.num_seconds()
+                ),
+                pool,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Only the account's counter is reset; the IP keeps its history so an attacker cannot
+    /// clear it by logging into an account of their own.
+    pub async fn record_success(login: &str, pool: &Data<PgPool>) -> Result<(), AppError> {
+        LoginAttemptRepo::clear(LOGIN_SCOPE, login, pool).await
+    }
+}
+
+/// Caps the mails `forgot_password` and `resend_verification` send, per submitted login and per
+/// client IP, so neither endpoint can flood an inbox or fill the token tables. Shares the
+/// `login_attempts` table with `LoginThrottle` under its own scopes.
+///
+/// Requests count whether or not the login exists, like failed logins.
+pub struct MailThrottle;
+
+impl MailThrottle {
+    /// Counts the request, or rejects it (429) while the login or the client IP is over its
+    /// limit for `mail_window`.
+    pub async fn hit(
+        login: &str,
+        client_ip: &str,
+        config: &AppConfig,
+        pool: &Data<PgPool>,
+    ) -> Result<(), AppError> {
+        let now = Utc::now().naive_utc();
+        let limits = [
+            (MAIL_LOGIN_SCOPE, login, config.mail_max_per_login),
+            (MAIL_IP_SCOPE, client_ip, config.mail_max_per_ip),
+        ];
+        for (scope, key, _) in limits {
+            if let Some(until) = LoginAttemptRepo::locked_until(scope, key, now, pool).await? {
+                return Err(AppError::TooManyRequests(
+                    String::from("Too many mail requests, try again later !!"),
+                    retry_after(until, now),
+                ));
+            }
+        }
+        let window = config.mail_window;
+        for (scope, key, limit) in limits {
+            let sent =
+                LoginAttemptRepo::record_failure(scope, key, now, window.num_seconds(), pool)
+                    .await?;
+            if sent >= limit {
+                LoginAttemptRepo::lock(scope, key, now + window, pool).await?;
+                warn!("Mail limit reached for {} {}", scope, key);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Peer address the throttles key on; forwarding headers are not trusted.
+pub fn client_ip(req: &HttpRequest) -> String {
+    req.connection_info()
+        .peer_addr()
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// `base` once `failures` reaches `threshold`, doubling with every further failure, capped at
+/// `max`. `None` below the threshold.
+pub fn lockout_duration(
+    failures: i32,
+    threshold: i32,
+    base: Duration,
+    max: Duration,
+) -> Option<Duration> {
+    if failures < threshold {
+        return None;
+    }
+    let doublings = (failures - threshold).min(30) as u32;
+    let seconds = base.num_seconds().saturating_mul(1i64 << doublings);
+    Some(Duration::seconds(seconds.min(max.num_seconds())))
+}
+
+fn retry_after(until: NaiveDateTime, now: NaiveDateTime) -> i64 {
+    let remaining = until - now;
+    (remaining.num_milliseconds() + 999).div_euclid(1000).max(1)
+}
+
+#[cfg(test)]
+mod tests_throttle {
+    use super::*;
+
+    #[test]
+    fn lockout_doubles_from_threshold_and_is_capped() {
+        let base = Duration::seconds(30);
+        let max = Duration::seconds(3600);
+        assert_eq!(lockout_duration(4, 5, base, max), None);
+        assert_eq!(
+            lockout_duration(5, 5, base, max),
+            Some(Duration::seconds(30))
+        );
+        assert_eq!(
+            lockout_duration(6, 5, base, max),
+            Some(Duration::seconds(60))
+        );
+        assert_eq!(
+            lockout_duration(8, 5, base, max),
+            Some(Duration::seconds(240))
+        );
+        assert_eq!(lockout_duration(500, 5, base, max), Some(max));
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_seconds() {
+        let now = Utc::now().naive_utc();
+        assert_eq!(retry_after(now + Duration::milliseconds(1500), now), 2);
+        assert_eq!(retry_after(now + Duration::seconds(30), now), 30);
+        assert_eq!(retry_after(now - Duration::seconds(5), now), 1);
+    }
+}
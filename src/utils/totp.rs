@@ -0,0 +1,129 @@
+use rand::RngCore;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use super::errors::AppError;
+use super::helpers::{constant_time_eq, sha256_hex};
+
+const DIGITS: usize = 6;
+const STEP_SECS: u64 = 30;
+/// Steps accepted on either side of the current one, to absorb clock drift.
+const SKEW_STEPS: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// 80 bits each, so the unsalted hashes cannot be brute-forced from a database dump.
+const RECOVERY_CODE_BYTES: usize = 10;
+
+/// Fresh 160-bit shared secret, base32 encoded as authenticator apps expect it.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::rng().fill_bytes(&mut bytes);
+    match Secret::Raw(bytes.to_vec()).to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns Secret::Encoded"),
+    }
+}
+
+/// RFC 6238 generator for a base32 `secret`, labelled with `issuer` and `account` in the
+/// otpauth URI.
+pub fn totp(secret: &str, issuer: &str, account: &str) -> Result<TOTP, AppError> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::Internal(format!("Invalid TOTP secret :: {:?}", e)))?;
+    // The otpauth label uses ':' as its separator.
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        SKEW_STEPS as u8,
+        STEP_SECS,
+        bytes,
+        Some(issuer.replace(':', "_")),
+        account.replace(':', "_"),
+    )
+    .map_err(|e| AppError::Internal(format!("Invalid TOTP parameters :: {:?}", e)))
+}
+
+/// Time step that `code` is valid for at `now` (unix seconds), looking `SKEW_STEPS` either
+/// way. Callers store the step so the same code cannot be used twice.
+pub fn matching_step(totp: &TOTP, code: &str, now: u64) -> Option<u64> {
+    let current = now / STEP_SECS;
+    let code = code.trim();
+    (current.saturating_sub(SKEW_STEPS)..=current + SKEW_STEPS)
+        .find(|step| constant_time_eq(&totp.generate(step * STEP_SECS), code))
+}
+
+/// Single-use recovery codes as `(shown to the user, stored hash)` pairs.
+pub fn generate_recovery_codes() -> Vec<(String, String)> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; RECOVERY_CODE_BYTES];
+            rand::rng().fill_bytes(&mut bytes);
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            let code = format!(
+                "{}-{}-{}-{}",
+                &hex[..5],
+                &hex[5..10],
+                &hex[10..15],
+                &hex[15..]
+            );
+            let hash = hash_recovery_code(&code);
+            (code, hash)
+        })
+        .collect()
+}
+
+/// Hash of a recovery code as typed; case, spaces and dashes are ignored.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    sha256_hex(&normalized)
+}
+
+#[cfg(test)]
+mod tests_totp {
+    use super::*;
+
+    // RFC 6238 appendix B test key, "12345678901234567890".
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn codes_match_the_rfc_vectors() {
+        let totp = totp(RFC_SECRET, "actix-crud", "alice").unwrap();
+        assert_eq!(totp.generate(59), "287082");
+        assert_eq!(totp.generate(1111111109), "081804");
+    }
+
+    #[test]
+    fn codes_are_accepted_one_step_either_way() {
+        let totp = totp(RFC_SECRET, "actix-crud", "alice").unwrap();
+        let code = totp.generate(1111111109);
+        let step = 1111111109 / STEP_SECS;
+        assert_eq!(matching_step(&totp, &code, 1111111109), Some(step));
+        assert_eq!(matching_step(&totp, &code, 1111111109 + 30), Some(step));
+        assert_eq!(matching_step(&totp, &code, 1111111109 - 30), Some(step));
+        assert_eq!(matching_step(&totp, &code, 1111111109 + 90), None);
+        assert_eq!(matching_step(&totp, "000000", 1111111109), None);
+    }
+
+    #[test]
+    fn generated_secret_round_trips() {
+        let secret = generate_secret();
+        let uri = totp(&secret, "actix-crud", "a:b").unwrap().get_url();
+        assert!(uri.starts_with("otpauth://totp/actix-crud:a_b?secret="));
+        assert!(uri.contains(&secret));
+    }
+
+    #[test]
+    fn recovery_codes_hash_independently_of_formatting() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        let (code, hash) = &codes[0];
+        assert_eq!(code.len(), RECOVERY_CODE_BYTES * 2 + 3);
+        assert_eq!(
+            &hash_recovery_code(&code.to_uppercase().replace('-', " ")),
+            hash
+        );
+        assert_ne!(hash_recovery_code(&codes[1].0), *hash);
+    }
+}
@@ -1,15 +1,58 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::validation::{not_blank, not_reserved_login, password_policy};
 
 #[derive(Serialize, Deserialize)]
 pub struct UserInfo {
-    pub user_id: usize,
+    pub user_id: i32,
+}
+
+/// The authenticated principal `authenticate_request` stores in request extensions.
+///
+/// Handlers receive it through its `FromRequest` impl instead of reading extensions directly.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthenticatedUser {
+    pub user_id: i32,
+    pub roles: Vec<String>,
+    pub scopes: Vec<String>,
+    pub token_id: Option<String>,
+    pub expires_at: i64,
+    /// Whether the credential is an interactive session rather than a personal token or key.
+    pub session: bool,
+}
+
+impl AuthenticatedUser {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+impl From<Claims> for AuthenticatedUser {
+    fn from(claims: Claims) -> Self {
+        AuthenticatedUser {
+            user_id: claims.sub,
+            roles: claims.roles,
+            scopes: claims.scope.split_whitespace().map(String::from).collect(),
+            token_id: Some(claims.jti),
+            expires_at: claims.exp,
+            session: claims.session,
+        }
+    }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Validate)]
 pub struct UserPayload {
+    #[validate(length(max = 100), custom(function = "not_blank"))]
     pub user_name: String,
+    #[validate(email)]
     pub user_email: String,
+    #[validate(length(max = 500))]
     pub user_address: String,
 }
 
@@ -19,26 +62,62 @@ pub struct UserDetail {
     pub user_payload: UserPayload,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Validate)]
 pub struct RegisterUser {
+    #[validate(length(max = 100), custom(function = "not_blank"))]
     pub user_name: String,
+    #[validate(custom(function = "password_policy"))]
     pub sec: String,
+    #[validate(
+        length(min = 3, max = 100),
+        custom(function = "not_blank"),
+        custom(function = "not_reserved_login")
+    )]
     pub user_login: String,
+    #[validate(length(max = 500))]
     pub address: String,
+    #[validate(email, length(max = 254))]
+    pub email: String,
 }
 
 #[derive(Serialize, Debug, Deserialize)]
 pub struct Claims {
     pub sub: i32,
     pub exp: i64,
-    // pub aud: String,
+    pub aud: String,
     pub iss: String,
     pub iat: i64,
+    pub jti: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Space-separated scopes, as in OAuth 2.0.
+    #[serde(default)]
+    pub scope: String,
+    /// Set on session access tokens only; personal tokens with the same scopes are not sessions.
+    #[serde(default)]
+    pub session: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Claims of the short-lived token `user_login` hands out when a second factor is still due.
+///
+/// Its audience differs from access tokens', so it is refused everywhere except `verify_mfa`.
+#[derive(Serialize, Debug, Deserialize)]
+pub struct MfaClaims {
+    pub sub: i32,
+    pub exp: i64,
+    pub aud: String,
+    pub iss: String,
+    pub iat: i64,
+    pub jti: String,
+}
+
+/// Only bounds are checked here; the password policy applies when a password is set, not when
+/// an existing one is presented.
+#[derive(Serialize, Deserialize, Debug, Validate)]
 pub struct UserLogin {
+    #[validate(length(min = 1, max = 100))]
     pub user_login: String,
+    #[validate(length(min = 1, max = 128))]
     pub sec: String,
 }
 
@@ -46,6 +125,9 @@ pub struct UserLogin {
 pub struct UserDetails {
     pub id: i32,
     pub sec: String,
+    pub roles: Vec<String>,
+    pub email_verified_at: Option<NaiveDateTime>,
+    pub totp_enabled: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
@@ -65,7 +147,256 @@ pub struct OrderDetails {
     pub created_at: NaiveDateTime,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate)]
 pub struct Order {
+    #[validate(length(max = 1000), custom(function = "not_blank"))]
     pub description: String,
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Tokens handed out when a session starts or is refreshed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionTokens {
+    pub token_type: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+#[derive(Debug)]
+pub enum RefreshOutcome {
+    Rotated { user_id: i32 },
+    Reused,
+    Invalid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct PersonalTokenRequest {
+    #[validate(length(max = 10))]
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PersonalToken {
+    pub token_type: String,
+    pub access_token: String,
+    pub scope: String,
+    pub expires_in: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ApiKeyRequest {
+    #[validate(length(min = 1, max = 100), custom(function = "not_blank"))]
+    pub name: String,
+    #[validate(length(max = 10))]
+    pub scopes: Vec<String>,
+    /// Omit for a key that never expires.
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiKeyInfo {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Returned once, at creation; `key` cannot be recovered afterwards.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiKeyCreated {
+    pub key: String,
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+}
+
+pub struct ApiKeyRecord {
+    pub id: i32,
+    pub user_id: i32,
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub user_active: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiKeyPath {
+    pub key_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasswordHashingStats {
+    pub available_slots: usize,
+    pub waiting: usize,
+    /// Jobs that got a slot, finished or not.
+    pub started: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ChangePassword {
+    #[validate(length(min = 1, max = 128))]
+    pub old_sec: String,
+    #[validate(custom(function = "password_policy"))]
+    pub new_sec: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ForgotPassword {
+    #[validate(length(min = 1, max = 100))]
+    pub user_login: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ResetPassword {
+    #[validate(length(min = 1, max = 200))]
+    pub token: String,
+    #[validate(custom(function = "password_policy"))]
+    pub new_sec: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VerifyEmail {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct ResendVerification {
+    #[validate(length(min = 1, max = 100))]
+    pub user_login: String,
+}
+
+/// What the MFA endpoints need to know about an account.
+#[derive(Debug)]
+pub struct MfaState {
+    pub user_login: String,
+    pub roles: Vec<String>,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+}
+
+/// Returned by enrollment; the secret is only enforced after `confirm_totp`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TotpSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Re-authentication for account-security changes.
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct CurrentPassword {
+    #[validate(length(min = 1, max = 128))]
+    pub sec: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct TotpCode {
+    #[validate(length(min = 6, max = 8))]
+    pub code: String,
+}
+
+/// Shown once, when TOTP is confirmed; only their hashes are stored.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Returned by `user_login` instead of a session when the account has TOTP enabled.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MfaChallenge {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: i64,
+}
+
+/// Second login step: the `mfa_token` plus either a TOTP `code` or a `recovery_code`.
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct MfaVerify {
+    #[validate(length(min = 1, max = 2048))]
+    pub mfa_token: String,
+    #[validate(length(min = 6, max = 8))]
+    pub code: Option<String>,
+    #[validate(length(min = 1, max = 32))]
+    pub recovery_code: Option<String>,
+}
+
+/// Claims of the signed cookie that carries an SSO login from `oidc_start` to `oidc_callback`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OidcLoginState {
+    pub state: String,
+    pub nonce: String,
+    /// PKCE code verifier; only its challenge is sent to the provider up front.
+    pub verifier: String,
+    pub exp: i64,
+    pub aud: String,
+    pub iss: String,
+}
+
+/// Query string the provider redirects back with.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OidcCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// The parts of the provider's discovery document we use.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub exp: i64,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+}
+
+/// A user as shown to themselves and to admins; secrets and security settings are left out.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserProfile {
+    pub id: i32,
+    pub user_name: String,
+    pub user_login: String,
+    pub address: String,
+    pub email: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Partial profile update; omitted fields keep their value. Login, email and password have
+/// their own flows and cannot be changed here.
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct UpdateProfile {
+    #[validate(length(max = 100), custom(function = "not_blank"))]
+    pub user_name: Option<String>,
+    #[validate(length(max = 500))]
+    pub address: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserPath {
+    pub user_id: i32,
+}
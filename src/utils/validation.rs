@@ -0,0 +1,186 @@
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+
+use actix_web::dev::Payload;
+use actix_web::web::Json;
+use actix_web::{Error, FromRequest, HttpRequest};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use super::constants::OIDC_LOGIN_PREFIX;
+use super::errors::AppError;
+
+pub const PASSWORD_MIN_LEN: usize = 8;
+pub const PASSWORD_MAX_LEN: usize = 128;
+
+/// One rejected field in a 422 response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// `Json<T>` that also runs `T`'s `validator` rules.
+///
+/// Malformed JSON is still reported by the `JsonConfig` error handler (400); JSON that parses
+/// but breaks a rule becomes `AppError::Unprocessable` (422) listing every offending field.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidatedJson<T> {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let Json(value) = json.await?;
+            value.validate().map_err(AppError::from)?;
+            Ok(ValidatedJson(value))
+        })
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields: Vec<FieldError> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errors)| {
+                errors.iter().map(move |error| FieldError {
+                    field: field.to_string(),
+                    message: error
+                        .message
+                        .as_ref()
+                        .map(|message| message.to_string())
+                        .unwrap_or_else(|| error.code.to_string()),
+                })
+            })
+            .collect();
+        fields.sort_by(|a, b| a.field.cmp(&b.field).then(a.message.cmp(&b.message)));
+        AppError::Unprocessable(fields)
+    }
+}
+
+/// Password policy for new passwords: 8 to 128 characters with at least one letter and one
+/// digit. Existing passwords are not re-checked at login.
+pub fn password_policy(sec: &str) -> Result<(), ValidationError> {
+    let length = sec.chars().count();
+    if !(PASSWORD_MIN_LEN..=PASSWORD_MAX_LEN).contains(&length) {
+        return Err(ValidationError::new("password_length").with_message(
+            format!(
+                "must be between {} and {} characters",
+                PASSWORD_MIN_LEN, PASSWORD_MAX_LEN
+            )
+            .into(),
+        ));
+    }
+    if !sec.chars().any(char::is_alphabetic) || !sec.chars().any(|c| c.is_ascii_digit()) {
+        return Err(ValidationError::new("password_strength")
+            .with_message("must contain a letter and a digit".into()));
+    }
+    Ok(())
+}
+
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::new("blank").with_message("must not be blank".into()));
+    }
+    Ok(())
+}
+
+/// Logins starting with `OIDC_LOGIN_PREFIX`, in any case, belong to SSO users.
+pub fn not_reserved_login(value: &str) -> Result<(), ValidationError> {
+    if value
+        .trim()
+        .to_ascii_lowercase()
+        .starts_with(OIDC_LOGIN_PREFIX)
+    {
+        return Err(ValidationError::new("reserved")
+            .with_message(format!("must not start with {}", OIDC_LOGIN_PREFIX).into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_validation {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, try_call_service, TestRequest};
+    use actix_web::{web, App, HttpResponse, ResponseError};
+
+    use crate::utils::types::{Order, RegisterUser};
+
+    #[test]
+    fn password_policy_requires_length_letter_and_digit() {
+        assert!(password_policy("abc123").is_err());
+        assert!(password_policy("abcdefgh").is_err());
+        assert!(password_policy("12345678").is_err());
+        assert!(password_policy("correct horse 9").is_ok());
+    }
+
+    #[test]
+    fn sso_logins_are_reserved() {
+        assert!(not_reserved_login("oidc_0123456789abcdef0123").is_err());
+        assert!(not_reserved_login(" OIDC_x").is_err());
+        assert!(not_reserved_login("oidc").is_ok());
+        assert!(not_reserved_login("alice_oidc_").is_ok());
+    }
+
+    #[test]
+    fn register_errors_are_listed_per_field() {
+        let payload = RegisterUser {
+            user_name: String::new(),
+            sec: String::from("short"),
+            user_login: String::from("alice"),
+            address: String::new(),
+            email: String::from("not-an-email"),
+        };
+        let err = AppError::from(payload.validate().unwrap_err());
+        assert_eq!(err.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        let AppError::Unprocessable(fields) = err else {
+            panic!("unexpected error {:?}", err);
+        };
+        let names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(names, vec!["email", "sec", "user_name"]);
+    }
+
+    #[actix_web::test]
+    async fn extractor_rejects_blank_description() {
+        async fn create(order: ValidatedJson<Order>) -> HttpResponse {
+            HttpResponse::Ok().body(order.into_inner().description)
+        }
+        let app = init_service(App::new().route("/orders", web::post().to(create))).await;
+
+        let blank = TestRequest::post()
+            .uri("/orders")
+            .set_json(serde_json::json!({"description": "   "}))
+            .to_request();
+        let status = match try_call_service(&app, blank).await {
+            Ok(resp) => resp.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let ok = TestRequest::post()
+            .uri("/orders")
+            .set_json(serde_json::json!({"description": "two pizzas"}))
+            .to_request();
+        assert_eq!(call_service(&app, ok).await.status(), StatusCode::OK);
+    }
+}